@@ -4,29 +4,282 @@ mod mods {
     include!("src/mods/mod.rs");
 }
 
-use std::{env, path::Path};
+#[cfg(feature = "std")]
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
 
+#[cfg(feature = "std")]
 use mods::{
-    resource::generate_resources_mapping,
-    resource_dir::resource_dir,
+    resource::{
+        generate_resources_map_and_list, generate_resources_map_and_list_relative,
+        generate_resources_mapping, generate_resources_slice, generate_resources_slice_relative,
+        EntryAction,
+    },
+    resource_dir::{resource_dir, ResourceDir},
     sets::{generate_resources_sets, SplitByCount},
 };
 
+#[cfg(not(feature = "std"))]
+fn main() {}
+
+#[cfg(feature = "std")]
 fn main() -> std::io::Result<()> {
     resource_dir("./tests").build()?;
 
     let out_dir = env::var("OUT_DIR").unwrap();
+    let out_dir = Path::new(&out_dir);
+
+    build_fixture(out_dir, "./tests", "relative", |resources| {
+        resources.with_relative_includes(true);
+    })?;
+    build_fixture(out_dir, "./tests", "custom", |resources| {
+        resources.with_resource_namespace("crate::custom");
+    })?;
+    build_fixture(out_dir, "./tests", "download", |resources| {
+        resources.with_download_names(true);
+    })?;
+    build_fixture(out_dir, "./tests", "cfg", |resources| {
+        resources
+            .with_count_per_module(1)
+            .with_cfg(|key| (key == "file1.txt").then(|| "target_os = \"none\"".into()));
+    })?;
+    build_fixture(out_dir, "./tests", "order", |resources| {
+        resources.with_count_per_module(3).with_order(true);
+    })?;
+    let manifest = out_dir.join("modified.txt");
+    std::fs::write(&manifest, "# key seconds\nfile1.txt 1700000000\n")?;
+    build_fixture(out_dir, "./tests", "modified", |resources| {
+        resources.with_modified_from_manifest(manifest);
+    })?;
+    build_fixture(out_dir, "./tests", "keys", |resources| {
+        resources.with_keys_const(true);
+    })?;
+    build_fixture(out_dir, "./tests", "mime", |resources| {
+        resources.with_mime_overrides(
+            [
+                (".INFO".into(), "text/x-info".into()),
+                ("html".into(), "text/html; charset=utf-8".into()),
+            ]
+            .into(),
+        );
+    })?;
+    let extensions = ["txt"];
+    build_fixture(out_dir, "./tests", "filter", |resources| {
+        resources.with_filter(move |path| {
+            path.is_dir()
+                || path.extension().map_or(false, |extension| {
+                    extensions.iter().any(|e| *e == extension)
+                })
+        });
+    })?;
+    build_fixture(out_dir, "./tests", "entry_action", |resources| {
+        resources.with_entry_action(|context| match context.key {
+            "file2.txt" => EntryAction::Skip,
+            "file3.info" => EntryAction::Rename("docs/file3.info".into()),
+            _ => EntryAction::Keep,
+        });
+    })?;
+    build_fixture(out_dir, "./tests", "globs", |resources| {
+        resources
+            .with_include_globs(["file*", "**/*.html"])
+            .with_exclude_globs(["*2.txt", "index.*"]);
+    })?;
+    build_fixture(out_dir, "./tests", "flat", |resources| {
+        resources
+            .with_flat_threshold(10)
+            .with_relative_includes(true);
+    })?;
+    build_fixture(out_dir, "./tests", "prefix", |resources| {
+        resources
+            .with_count_per_module(2)
+            .with_prefix_argument(true);
+    })?;
+
+    let inline_dir = write_fixture_dir(
+        out_dir,
+        "inline",
+        &[
+            ("tiny.bin", b"\0\"\\\xff"),
+            ("tiny.txt", b"tiny \"text\"\n"),
+            ("large.txt", &[b'a'; 64]),
+        ],
+    )?;
+    build_fixture(out_dir, inline_dir, "inline", |resources| {
+        resources.with_inline_threshold(64);
+    })?;
+
+    let immutable_dir = write_fixture_dir(
+        out_dir,
+        "immutable",
+        &[("app.abc123.js", b""), ("index.html", b"")],
+    )?;
+    build_fixture(out_dir, immutable_dir, "immutable", |resources| {
+        resources.with_immutable(mods::resource::is_hashed_file_name);
+    })?;
+
+    let charset_dir = write_fixture_dir(
+        out_dir,
+        "charset",
+        &[
+            ("utf8.txt", "caf\u{e9}\n".as_bytes()),
+            ("latin1.csv", b"caf\xe9,cr\xe8me br\xfbl\xe9e,na\xefve\n"),
+            ("image.png", b""),
+        ],
+    )?;
+    build_fixture(out_dir, &charset_dir, "charset", |resources| {
+        resources.with_charset(true);
+    })?;
+
+    build_multi_fixtures(out_dir, &charset_dir)?;
+
+    let site_dir = write_fixture_dir(
+        out_dir,
+        "site",
+        &[
+            ("index.html", b"root"),
+            ("guide/index.html", b"guide"),
+            ("guide/intro.html", b"intro"),
+            ("api/reference.html", b"reference"),
+        ],
+    )?;
+    build_fixture(out_dir, site_dir, "site", |_| {})?;
+
+    let sizes_dir = write_fixture_dir(
+        out_dir,
+        "sizes",
+        &[
+            ("a.bin", &[0; 60]),
+            ("b.bin", &[0; 60]),
+            ("c.bin", &[0; 60]),
+            ("d.bin", &[0; 10]),
+        ],
+    )?;
+    build_fixture(out_dir, sizes_dir, "sizes", |resources| {
+        resources.with_order(true).with_max_bytes_per_module(100);
+    })?;
+
+    let top_dirs_dir = write_fixture_dir(
+        out_dir,
+        "top_dirs",
+        &[
+            ("css/app.css", b""),
+            ("css/theme.css", b""),
+            ("js/app.js", b""),
+            ("js/vendor/lib.js", b""),
+            ("img/logo.png", b""),
+            ("index.html", b""),
+        ],
+    )?;
+    build_fixture(out_dir, top_dirs_dir, "top_dirs", |resources| {
+        resources.with_parallel_modules(3);
+    })?;
+
+    #[cfg(unix)]
+    {
+        let query_dir = write_fixture_dir(
+            out_dir,
+            "query",
+            &[
+                ("app.js?v=123", b"versioned"),
+                ("style.css?v=1", b"versioned"),
+                ("style.css", b"exact"),
+            ],
+        )?;
+        build_fixture(out_dir, query_dir, "query", |resources| {
+            resources.with_query_aliases(true);
+        })?;
+    }
+
+    #[cfg(unix)]
+    write_fake_package_managers(out_dir)?;
+
+    build_feature_fixtures(out_dir)?;
+
+    build_function_fixtures(out_dir)
+}
+
+/// Writes fake `npm`, `pnpm`, `yarn` and `bun` executables of `NpmBuild` doctests into `fake_npm`.
+///
+/// Run in the package directory, they log their name and arguments to `npm.log`,
+/// print their arguments and log `NODE_ENV` and `REGISTRY_TOKEN` variables to `env.log`.
+/// `install` and `ci` create `node_modules`, `run SCRIPT` writes the name of the executable
+/// to `dist/SCRIPT.txt`, the `fail` script exits with status 3.
+#[cfg(all(feature = "std", unix))]
+fn write_fake_package_managers(out_dir: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    const SCRIPT: &str = r#"#!/bin/sh
+name=$(basename "$0")
+echo "$name $*" >> npm.log
+echo "$*"
+[ -z "$NODE_ENV" ] || echo "$NODE_ENV $REGISTRY_TOKEN" >> env.log
+case "$1" in
+install | ci) mkdir -p node_modules ;;
+run)
+    [ "$2" != fail ] || exit 3
+    mkdir -p dist
+    echo "$name" > "dist/$2.txt"
+    ;;
+esac
+"#;
+
+    let dir = write_fixture_dir(
+        out_dir,
+        "fake_npm",
+        &[
+            ("npm", SCRIPT.as_bytes()),
+            ("pnpm", SCRIPT.as_bytes()),
+            ("yarn", SCRIPT.as_bytes()),
+            ("bun", SCRIPT.as_bytes()),
+        ],
+    )?;
+    for executable in ["npm", "pnpm", "yarn", "bun"] {
+        std::fs::set_permissions(dir.join(executable), std::fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
+
+/// Generates fixtures of generation functions.
+#[cfg(feature = "std")]
+fn build_function_fixtures(out_dir: &Path) -> std::io::Result<()> {
+    generate_resources_mapping("./tests", None, out_dir.join("generated_mapping.rs"))?;
+
+    generate_resources_map_and_list(
+        "./tests",
+        None,
+        out_dir.join("generated_map_and_list.rs"),
+        "generate_map",
+        "generate_list",
+    )?;
+
+    generate_resources_map_and_list_relative(
+        "./tests",
+        None,
+        out_dir.join("generated_map_and_list_relative.rs"),
+        "generate_map_relative",
+        "generate_list_relative",
+    )?;
 
-    generate_resources_mapping(
+    generate_resources_slice(
         "./tests",
         None,
-        Path::new(&out_dir).join("generated_mapping.rs"),
+        out_dir.join("generated_slice.rs"),
+        "RESOURCES",
+    )?;
+
+    generate_resources_slice_relative(
+        "./tests",
+        None,
+        out_dir.join("generated_slice_relative.rs"),
+        "RESOURCES",
     )?;
 
     generate_resources_sets(
         "./tests",
         None,
-        Path::new(&out_dir).join("generated_sets.rs"),
+        out_dir.join("generated_sets.rs"),
         "sets",
         "generate",
         &mut SplitByCount::new(2),
@@ -34,3 +287,139 @@ fn main() -> std::io::Result<()> {
 
     Ok(())
 }
+
+/// Generates fixtures of several functions in one file, gated by `cfg` predicates
+/// or bundles generated from `./tests` and `dark_dir`.
+#[cfg(feature = "std")]
+fn build_multi_fixtures(out_dir: &Path, dark_dir: &Path) -> std::io::Result<()> {
+    for (generated_fn, append) in [("generate_app", false), ("generate_docs", true)] {
+        let mut multi = resource_dir("./tests");
+        multi
+            .with_generated_filename(out_dir.join("generated_multi.rs"))
+            .with_generated_fn(generated_fn)
+            .with_append(append);
+        multi.build()?;
+    }
+
+    for (generated_fn, fn_cfg, append) in [
+        ("generate_gated", "not(target_os = \"none\")", false),
+        ("generate_gated_off", "target_os = \"none\"", true),
+    ] {
+        let mut gated = resource_dir("./tests");
+        gated
+            .with_generated_filename(out_dir.join("generated_gated.rs"))
+            .with_generated_fn(generated_fn)
+            .with_fn_cfg(fn_cfg)
+            .with_append(append);
+        gated.build()?;
+    }
+
+    for (dir, generated_fn, bundle_type, append) in [
+        (Path::new("./tests"), "generate_light", "Light", false),
+        (dark_dir, "generate_dark", "Dark", true),
+    ] {
+        let mut bundle = resource_dir(dir);
+        bundle
+            .with_generated_filename(out_dir.join("generated_bundles.rs"))
+            .with_generated_fn(generated_fn)
+            .with_bundle_type(bundle_type)
+            .with_append(append);
+        bundle.build()?;
+    }
+
+    Ok(())
+}
+
+/// Generates fixtures of optional features.
+#[cfg(feature = "std")]
+#[allow(unused_variables, clippy::unnecessary_wraps)]
+fn build_feature_fixtures(out_dir: &Path) -> std::io::Result<()> {
+    #[cfg(feature = "fxhash")]
+    build_fixture(out_dir, "./tests", "fxhash", |resources| {
+        resources.with_key_hasher(mods::resource::KeyHasher::FxHash);
+    })?;
+
+    #[cfg(feature = "integrity")]
+    build_fixture(out_dir, "./tests", "integrity", |resources| {
+        resources
+            .with_manifest_hash(true)
+            .with_etags(true)
+            .with_sri(true);
+    })?;
+
+    #[cfg(feature = "matchit")]
+    build_fixture(out_dir, "./tests", "router", |resources| {
+        resources.with_router_fn("routes");
+    })?;
+
+    #[cfg(feature = "unicode")]
+    {
+        let unicode_dir = write_fixture_dir(out_dir, "unicode", &[("cafe\u{301}.txt", b"")])?;
+        build_fixture(out_dir, unicode_dir, "unicode", |resources| {
+            resources.with_unicode_normalization(mods::resource::NormalizationForm::Nfc);
+        })?;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let modes_dir = write_fixture_dir(
+            out_dir,
+            "modes",
+            &[("bin/init", b"#!/bin/sh\n"), ("etc/hostname", b"device\n")],
+        )?;
+        for (path, mode) in [("bin/init", 0o755), ("etc/hostname", 0o644)] {
+            std::fs::set_permissions(modes_dir.join(path), std::fs::Permissions::from_mode(mode))?;
+        }
+        build_fixture(out_dir, modes_dir, "modes", |resources| {
+            resources.with_modes(true);
+        })?;
+    }
+
+    #[cfg(all(feature = "xattr", unix))]
+    {
+        let xattr_dir = write_fixture_dir(out_dir, "xattr", &[("file.txt", b"xattr")])?;
+        xattr::set(xattr_dir.join("file.txt"), "user.static-files", b"embedded")?;
+        build_fixture(out_dir, xattr_dir, "xattr", |resources| {
+            resources.with_xattrs(["user.static-files"]);
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Generates resources of `dir` configured by `configure`
+/// into `generated_{name}.rs` with function `generate_{name}`.
+#[cfg(feature = "std")]
+fn build_fixture<P: AsRef<Path>>(
+    out_dir: &Path,
+    dir: P,
+    name: &str,
+    configure: impl FnOnce(&mut ResourceDir),
+) -> std::io::Result<()> {
+    let mut resources = resource_dir(dir);
+    resources
+        .with_generated_filename(out_dir.join(format!("generated_{name}.rs")))
+        .with_generated_fn(format!("generate_{name}"));
+    configure(&mut resources);
+    resources.build()
+}
+
+/// Writes `files` into directory `name` and returns its path.
+#[cfg(feature = "std")]
+fn write_fixture_dir(
+    out_dir: &Path,
+    name: &str,
+    files: &[(&str, &[u8])],
+) -> std::io::Result<PathBuf> {
+    let dir = out_dir.join(name);
+    for (file_name, content) in files {
+        let path = dir.join(file_name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, content)?;
+    }
+    Ok(dir)
+}
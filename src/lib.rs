@@ -1,11 +1,33 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![doc(test(no_crate_inject))]
 #![doc = include_str!("../README.md")]
 
 mod mods;
 
+pub use crate::mods::resource::{self, Resource};
+#[cfg(feature = "std")]
 pub use crate::mods::{
+    crates,
+    export::{export_tar, export_tar_with_filter},
     npm_build::{npm_resource_dir, NpmBuild},
-    resource::{self, Resource},
     resource_dir::{resource_dir, ResourceDir},
     sets,
 };
+
+#[cfg(feature = "fxhash")]
+pub use rustc_hash::FxBuildHasher;
+
+#[cfg(feature = "cpio")]
+pub use crate::mods::resource_cpio::resource_cpio;
+
+#[cfg(feature = "git")]
+pub use crate::mods::resource_git::resource_git;
+
+#[cfg(feature = "matchit")]
+pub use matchit;
+
+#[cfg(feature = "test-support")]
+pub use crate::mods::test_support;
+
+#[cfg(feature = "watch")]
+pub use crate::mods::watch::{watch, Watch};
@@ -0,0 +1,209 @@
+/*!
+Support for crate based generations. Use it for very large data sets,
+when compilation of a single generated crate is the bottleneck.
+
+Resources are split into group crates compiled independently (and in parallel) by `cargo`,
+and an aggregator crate re-exporting them and merging their resources.
+
+Crate manifests must exist before `cargo` resolves dependencies, so crates can't be generated
+from `build.rs` of a dependent crate. Generate them with a separate tool (e.g. `xtask`) and
+make them workspace members, depending on the aggregator crate:
+
+```toml
+[workspace]
+members = ["app", "assets/assets", "assets/assets_1", "assets/assets_2"]
+```
+
+```toml
+# app/Cargo.toml
+[dependencies]
+assets = { path = "../assets/assets" }
+```
+ */
+use std::{
+    fs::{self, File, Metadata},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use super::resource::{
+    collect_resources, generate_function_end, generate_function_header, generate_resource_insert,
+    generate_uses, generate_variable_header, generate_variable_return, InsertOptions, KeyHasher,
+    DEFAULT_RESOURCE_NAMESPACE, DEFAULT_VARIABLE_NAME,
+};
+use super::sets::{split_resources, SetModuleNaming, SetSplitStrategie};
+
+/// Generate crates for resources of `project_dir` using `filter` in `output_dir`.
+///
+/// Group crates are named `{crate_name}_1`, `{crate_name}_2`, etc. and split by `set_split_strategy`.
+/// Aggregator crate `crate_name` re-exports them and provides function `generate`
+/// returning resources of all group crates. `crate_name` must be a valid identifier.
+///
+/// ```rust
+/// use std::{env, fs, path::Path};
+/// use static_files::{crates::generate_resources_crates, sets::SplitByCount};
+///
+/// let out_dir = env::var("OUT_DIR").unwrap();
+/// let output_dir = Path::new(&out_dir).join("crates");
+/// let _ = fs::remove_dir_all(&output_dir);
+///
+/// generate_resources_crates("./tests", None, &output_dir, "assets", &mut SplitByCount::new(2))
+///     .unwrap();
+///
+/// for group in ["assets_1", "assets_2"] {
+///     let manifest = fs::read_to_string(output_dir.join(group).join("Cargo.toml")).unwrap();
+///     assert!(manifest.contains(&format!("name = \"{group}\"")));
+///
+///     let lib = fs::read_to_string(output_dir.join(group).join("src/lib.rs")).unwrap();
+///     assert!(lib.contains("pub fn generate("));
+/// }
+/// assert!(!output_dir.join("assets_3").exists());
+///
+/// let manifest = fs::read_to_string(output_dir.join("assets/Cargo.toml")).unwrap();
+/// assert!(manifest.contains("assets_1 = { path = \"../assets_1\" }"));
+/// assert!(manifest.contains("assets_2 = { path = \"../assets_2\" }"));
+///
+/// let lib = fs::read_to_string(output_dir.join("assets/src/lib.rs")).unwrap();
+/// assert!(lib.contains("pub use ::assets_1;"));
+/// assert!(lib.contains("pub use ::assets_2;"));
+/// assert!(lib.contains("::assets_1::generate(&mut r);"));
+/// assert!(lib.contains("::assets_2::generate(&mut r);"));
+/// ```
+///
+/// # Errors
+/// Returns an error if resources can't be read or crates can't be written.
+pub fn generate_resources_crates<P, O, S>(
+    project_dir: P,
+    filter: Option<fn(p: &Path) -> bool>,
+    output_dir: O,
+    crate_name: &str,
+    set_split_strategy: &mut S,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    O: AsRef<Path>,
+    S: SetSplitStrategie,
+{
+    generate_resources_crates_with_filter(
+        project_dir,
+        |path| filter.map_or(true, |filter| filter(path)),
+        output_dir,
+        crate_name,
+        set_split_strategy,
+    )
+}
+
+/// Same as [`generate_resources_crates`], but `filter` can be a closure capturing its environment.
+///
+/// # Errors
+/// Returns an error if [`generate_resources_crates`] does.
+pub fn generate_resources_crates_with_filter<P, F, O, S>(
+    project_dir: P,
+    filter: F,
+    output_dir: O,
+    crate_name: &str,
+    set_split_strategy: &mut S,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    F: Fn(&Path) -> bool,
+    O: AsRef<Path>,
+    S: SetSplitStrategie,
+{
+    let resources = collect_resources(&project_dir, Some(filter))?;
+    let groups = split_resources(
+        &resources,
+        set_split_strategy,
+        &SetModuleNaming::Prefix(format!("{crate_name}_")),
+        project_dir.as_ref(),
+        &InsertOptions::default(),
+    )?;
+
+    let output_dir = output_dir.as_ref();
+    for (group_name, group_resources) in &groups {
+        let mut lib = create_crate(output_dir, group_name, &[])?;
+        generate_group_lib(&mut lib, project_dir.as_ref(), group_resources)?;
+    }
+
+    let group_names = groups
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>();
+    let mut lib = create_crate(output_dir, crate_name, &group_names)?;
+
+    for group_name in &group_names {
+        writeln!(lib, "pub use ::{group_name};")?;
+    }
+    generate_function_header(
+        &mut lib,
+        "generate",
+        KeyHasher::default(),
+        DEFAULT_RESOURCE_NAMESPACE,
+    )?;
+    generate_variable_header(&mut lib, DEFAULT_VARIABLE_NAME, KeyHasher::default())?;
+    for group_name in &group_names {
+        writeln!(
+            lib,
+            "::{group_name}::generate(&mut {DEFAULT_VARIABLE_NAME});"
+        )?;
+    }
+    generate_variable_return(&mut lib, DEFAULT_VARIABLE_NAME)?;
+    generate_function_end(&mut lib)
+}
+
+/// Writes `Cargo.toml` of crate `crate_name` with path dependencies on `group_names`
+/// and returns created `src/lib.rs`.
+fn create_crate(output_dir: &Path, crate_name: &str, group_names: &[&str]) -> io::Result<File> {
+    let crate_dir = output_dir.join(crate_name);
+    fs::create_dir_all(crate_dir.join("src"))?;
+
+    let mut manifest = File::create(crate_dir.join("Cargo.toml"))?;
+    writeln!(
+        manifest,
+        "\
+[package]
+name = \"{crate_name}\"
+version = \"0.0.0\"
+edition = \"2021\"
+publish = false
+
+[dependencies]
+static-files = {{ version = \"{}\", default-features = false }}",
+        env!("CARGO_PKG_VERSION"),
+    )?;
+    for group_name in group_names {
+        writeln!(manifest, "{group_name} = {{ path = \"../{group_name}\" }}")?;
+    }
+
+    File::create(crate_dir.join("src/lib.rs"))
+}
+
+fn generate_group_lib(
+    lib: &mut File,
+    project_dir: &Path,
+    resources: &[&(PathBuf, Metadata)],
+) -> io::Result<()> {
+    generate_uses(lib, DEFAULT_RESOURCE_NAMESPACE)?;
+    writeln!(
+        lib,
+        "\
+use ::std::collections::HashMap;
+use {DEFAULT_RESOURCE_NAMESPACE}::Resource;
+#[allow(clippy::unreadable_literal)]
+pub fn generate({DEFAULT_VARIABLE_NAME}: &mut HashMap<&'static str, Resource>) {{",
+    )?;
+
+    for resource in resources {
+        generate_resource_insert(
+            lib,
+            &project_dir,
+            DEFAULT_VARIABLE_NAME,
+            resource,
+            None,
+            &InsertOptions::default(),
+            None,
+        )?;
+    }
+
+    generate_function_end(lib)
+}
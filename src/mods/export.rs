@@ -0,0 +1,135 @@
+/*!
+Export of collected resources.
+ */
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use super::resource::{collect_resources, resource_key};
+
+const TAR_BLOCK_SIZE: usize = 512;
+const TAR_FILE_MODE: u64 = 0o644;
+const TAR_MTIME: u64 = 0;
+
+/// Writes resources of `project_dir` using `filter` as a reproducible tar archive to `writer`.
+///
+/// Entries are the files to be embedded, named by their resource keys and sorted by name.
+/// Modification time, owner and permissions are fixed, so the same files produce
+/// byte-identical archives on any machine.
+///
+/// ```rust
+/// use static_files::export_tar;
+///
+/// let mut first = vec![];
+/// export_tar("./tests", None, &mut first).unwrap();
+/// let mut second = vec![];
+/// export_tar("./tests", None, &mut second).unwrap();
+///
+/// assert_eq!(first, second);
+/// assert_eq!(first.len() % 512, 0);
+/// assert_eq!(&first[..9], b"file1.txt");
+/// assert_eq!(&first[257..263], b"ustar\0");
+/// ```
+///
+/// # Errors
+/// Returns an error if resources can't be read or written,
+/// or a resource key doesn't fit the tar header.
+pub fn export_tar<P, W>(
+    project_dir: P,
+    filter: Option<fn(p: &Path) -> bool>,
+    writer: W,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    W: Write,
+{
+    export_tar_with_filter(
+        project_dir,
+        |path| filter.map_or(true, |filter| filter(path)),
+        writer,
+    )
+}
+
+/// Same as [`export_tar`], but `filter` can be a closure capturing its environment.
+///
+/// # Errors
+/// Returns an error if [`export_tar`] does.
+pub fn export_tar_with_filter<P, F, W>(project_dir: P, filter: F, mut writer: W) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    F: Fn(&Path) -> bool,
+    W: Write,
+{
+    let mut resources = collect_resources(&project_dir, Some(filter))?
+        .into_iter()
+        .map(|(path, _)| (resource_key(&project_dir, &path), path))
+        .collect::<Vec<_>>();
+    resources.sort();
+
+    for (key, path) in resources {
+        let data = fs::read(path)?;
+        writer.write_all(&tar_header(&key, data.len() as u64)?)?;
+        writer.write_all(&data)?;
+        let padding = (TAR_BLOCK_SIZE - data.len() % TAR_BLOCK_SIZE) % TAR_BLOCK_SIZE;
+        writer.write_all(&[0; TAR_BLOCK_SIZE][..padding])?;
+    }
+
+    writer.write_all(&[0; 2 * TAR_BLOCK_SIZE])
+}
+
+/// Returns ustar header of regular file `name` with `size` bytes.
+fn tar_header(name: &str, size: u64) -> io::Result<[u8; TAR_BLOCK_SIZE]> {
+    let (prefix, name) = split_tar_name(name)?;
+
+    let mut header = [0; TAR_BLOCK_SIZE];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    write_octal(&mut header[100..108], TAR_FILE_MODE)?;
+    write_octal(&mut header[108..116], 0)?;
+    write_octal(&mut header[116..124], 0)?;
+    write_octal(&mut header[124..136], size)?;
+    write_octal(&mut header[136..148], TAR_MTIME)?;
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+    header[148..156].copy_from_slice(b"        ");
+    let checksum = header.iter().map(|&byte| u64::from(byte)).sum();
+    write_octal(&mut header[148..155], checksum)?;
+
+    Ok(header)
+}
+
+/// Splits `name` into ustar `prefix` (up to 155 bytes) and `name` (up to 100 bytes) fields.
+fn split_tar_name(name: &str) -> io::Result<(&str, &str)> {
+    if name.len() <= 100 {
+        return Ok(("", name));
+    }
+
+    name.match_indices('/')
+        .map(|(index, _)| (&name[..index], &name[index + 1..]))
+        .find(|(prefix, name)| prefix.len() <= 155 && name.len() <= 100)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("resource key {name:?} is too long for tar header"),
+            )
+        })
+}
+
+/// Writes zero-padded octal `value` followed by NUL to `field`.
+fn write_octal(field: &mut [u8], value: u64) -> io::Result<()> {
+    let digits = format!("{value:0width$o}", width = field.len() - 1);
+    if digits.len() >= field.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("value {value} is too large for tar header"),
+        ));
+    }
+
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+    field[digits.len()] = 0;
+    Ok(())
+}
@@ -1,4 +1,21 @@
+#[cfg(feature = "std")]
+pub mod crates;
+#[cfg(feature = "std")]
+pub mod export;
+#[cfg(feature = "std")]
 pub mod npm_build;
+#[cfg(feature = "std")]
+mod preload;
 pub mod resource;
+#[cfg(feature = "cpio")]
+pub mod resource_cpio;
+#[cfg(feature = "std")]
 pub mod resource_dir;
+#[cfg(feature = "git")]
+pub mod resource_git;
+#[cfg(feature = "std")]
 pub mod sets;
+#[cfg(feature = "test-support")]
+pub mod test_support;
+#[cfg(feature = "watch")]
+pub mod watch;
@@ -2,12 +2,15 @@
 `npm` support.
 */
 use std::{
+    env,
+    ffi::{OsStr, OsString},
+    fs::{self, File},
     io::{self},
     path::{Path, PathBuf},
-    process::{Command, Stdio},
+    process::{Command, ExitStatus, Output, Stdio},
 };
 
-use super::resource_dir::ResourceDir;
+use super::{resource_dir::ResourceDir, sets::fnv1a_hash};
 
 #[cfg(not(windows))]
 const NPM_CMD: &str = "npm";
@@ -15,6 +18,20 @@ const NPM_CMD: &str = "npm";
 #[cfg(windows)]
 const NPM_CMD: &str = "npm.cmd";
 
+#[cfg(not(windows))]
+const BUN_CMD: &str = "bun";
+
+#[cfg(windows)]
+const BUN_CMD: &str = "bun.exe";
+
+/// Package managers detected by their lock files, in order of precedence.
+const LOCK_FILES: &[(&str, &str)] = &[
+    ("pnpm-lock.yaml", "pnpm"),
+    ("yarn.lock", "yarn"),
+    ("bun.lockb", "bun"),
+    ("bun.lock", "bun"),
+];
+
 /// Generate resources with run of `npm install` prior to collecting
 /// resources in `resource_dir`.
 ///
@@ -53,13 +70,70 @@ pub fn npm_resource_dir<P: AsRef<Path>>(resource_dir: P) -> io::Result<ResourceD
 /// ```rust#ignore
 /// include!(concat!(env!("OUT_DIR"), "/generated.rs"));
 /// ```
+///
+/// Packages built with different package managers, e.g. in a monorepo, are merged into
+/// one map with [`ResourceDir::with_merged`], or appended to one generated file,
+/// each with its own function:
+///
+/// ```rust
+/// # #[cfg(unix)]
+/// # {
+/// use std::{env, fs, path::Path};
+/// use static_files::NpmBuild;
+///
+/// let out_dir = env::var("OUT_DIR").unwrap();
+/// let out_dir = Path::new(&out_dir);
+/// let generated_filename = out_dir.join("generated_npm_mixed.rs");
+///
+/// // Fake package managers writing their names to `dist/SCRIPT.txt`.
+/// let packages = [("pnpm", "admin"), ("yarn", "shop")];
+/// for (index, (executable, generated_fn)) in packages.iter().enumerate() {
+///     let package_dir = out_dir.join("npm_mixed").join(generated_fn);
+///     fs::create_dir_all(&package_dir).unwrap();
+///
+///     let mut resources = NpmBuild::new(&package_dir)
+///         .executable(out_dir.join("fake_npm").join(executable).to_str().unwrap())
+///         .install()
+///         .unwrap()
+///         .run(generated_fn)
+///         .unwrap()
+///         .target(package_dir.join("dist"))
+///         .to_resource_dir();
+///     resources
+///         .with_generated_filename(&generated_filename)
+///         .with_generated_fn(*generated_fn)
+///         .with_append(index > 0);
+///     resources.build().unwrap();
+/// }
+///
+/// let generated = fs::read_to_string(&generated_filename).unwrap();
+/// assert!(generated.lines().any(|line| line.ends_with("::admin;")));
+/// assert!(generated.lines().any(|line| line.ends_with("::shop;")));
+///
+/// let admin = fs::read_to_string(out_dir.join("admin_sets/set_1.rs")).unwrap();
+/// assert!(admin.contains("\"admin.txt\""));
+/// let shop = fs::read_to_string(out_dir.join("shop_sets/set_1.rs")).unwrap();
+/// assert!(shop.contains("\"shop.txt\""));
+/// let built_by = fs::read_to_string(out_dir.join("npm_mixed/shop/dist/shop.txt")).unwrap();
+/// assert_eq!(built_by, "yarn\n");
+/// # }
+/// ```
 #[derive(Default, Debug)]
 pub struct NpmBuild {
     package_json_dir: PathBuf,
     executable: String,
+    envs: Vec<(OsString, OsString)>,
     target_dir: Option<PathBuf>,
     stderr: Option<Stdio>,
     stdout: Option<Stdio>,
+    stderr_file: Option<File>,
+    stdout_file: Option<File>,
+    /// Cargo directives printed with change detection instructions.
+    #[cfg(feature = "change-detection")]
+    cargo_directives: Vec<String>,
+    /// Whether change detection instructions are generated.
+    #[cfg(feature = "change-detection")]
+    change_detection_generated: bool,
 }
 
 impl NpmBuild {
@@ -71,6 +145,11 @@ impl NpmBuild {
         }
     }
 
+    /// Creates build with [bun](https://bun.sh) executable.
+    pub fn bun<P: AsRef<Path>>(package_json_dir: P) -> Self {
+        Self::new(package_json_dir).executable(BUN_CMD)
+    }
+
     /// Allow the user to set their own npm-like executable (like yarn, for instance)
     #[must_use]
     pub fn executable(self, executable: &str) -> Self {
@@ -78,6 +157,77 @@ impl NpmBuild {
         Self { executable, ..self }
     }
 
+    /// Selects the npm-like executable used for the package.
+    ///
+    /// The package manager running the build, as reported by `npm_config_user_agent`,
+    /// is used first. Otherwise `pnpm`, `yarn` or `bun` is selected by its lock file
+    /// in `package_json_dir`, falling back to `npm`. With [`NpmBuild::change_detection`]
+    /// Cargo reruns the build script when `npm_config_user_agent` changes.
+    ///
+    /// ```rust
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::{env, fs, path::Path};
+    /// use static_files::NpmBuild;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let out_dir = Path::new(&out_dir);
+    /// let packages_dir = out_dir.join("npm_detect");
+    /// let _ = fs::remove_dir_all(&packages_dir);
+    ///
+    /// // Fake package managers logging their names to `npm.log`.
+    /// let path = env::var_os("PATH").unwrap_or_default();
+    /// let paths = std::iter::once(out_dir.join("fake_npm")).chain(env::split_paths(&path));
+    /// env::set_var("PATH", env::join_paths(paths).unwrap());
+    /// env::remove_var("npm_config_user_agent");
+    ///
+    /// let installed = |lock_file: Option<&str>| {
+    ///     let package_dir = packages_dir.join(lock_file.unwrap_or("none"));
+    ///     fs::create_dir_all(&package_dir).unwrap();
+    ///     if let Some(lock_file) = lock_file {
+    ///         fs::write(package_dir.join(lock_file), "").unwrap();
+    ///     }
+    ///     NpmBuild::new(&package_dir).auto_executable().install().unwrap();
+    ///     fs::read_to_string(package_dir.join("npm.log")).unwrap()
+    /// };
+    ///
+    /// assert_eq!(installed(Some("pnpm-lock.yaml")), "pnpm install\n");
+    /// assert_eq!(installed(Some("yarn.lock")), "yarn install\n");
+    /// assert_eq!(installed(Some("bun.lockb")), "bun install\n");
+    /// assert_eq!(installed(None), "npm install\n");
+    ///
+    /// env::set_var("npm_config_user_agent", "yarn/1.22.19 npm/? node/v18.17.0 linux x64");
+    /// assert_eq!(installed(Some("package-lock.json")), "yarn install\n");
+    /// # }
+    /// ```
+    #[must_use]
+    #[cfg_attr(not(feature = "change-detection"), allow(unused_mut))]
+    pub fn auto_executable(mut self) -> Self {
+        #[cfg(feature = "change-detection")]
+        self.cargo_directive("rerun-if-env-changed=npm_config_user_agent".into());
+
+        let user_agent = env::var("npm_config_user_agent").ok();
+        let executable = user_agent
+            .as_deref()
+            .and_then(|user_agent| user_agent.split('/').next())
+            .filter(|name| ["npm", "pnpm", "yarn", "bun"].contains(name))
+            .or_else(|| {
+                LOCK_FILES
+                    .iter()
+                    .find(|(lock_file, _)| self.package_json_dir.join(lock_file).is_file())
+                    .map(|(_, executable)| *executable)
+            });
+
+        match executable {
+            Some("npm") | None => Self {
+                executable: String::from(NPM_CMD),
+                ..self
+            },
+            Some("bun") => self.executable(BUN_CMD),
+            Some(executable) => self.executable(executable),
+        }
+    }
+
     /// Generates change detection instructions.
     ///
     /// It includes `package.json` directory, ignores by default `node_modules`, `package.json` and `package-lock.json` and target directory.
@@ -87,10 +237,15 @@ impl NpmBuild {
     /// Reason is the same, `npm` touches `dist` each time and it touches the parent directory which in its turn triggers the build each time.
     /// For complete example see: [Angular Router Sample](https://github.com/kilork/actix-web-static-files-example-angular-router).
     /// If default behavior does not work for you, you can use [change-detection](https://crates.io/crates/change-detection) directly.
+    ///
+    /// Cargo directives of other methods, like the lock file of [`NpmBuild::install_if_needed`]
+    /// or the variable of [`NpmBuild::target_from_env`], are printed only with these instructions,
+    /// as any of them disables the default rerun of the build script on changes in the package.
+    /// `SOURCE_DATE_EPOCH` environment variable clamping modification times is watched too.
     #[cfg(feature = "change-detection")]
     #[must_use]
     #[allow(clippy::missing_panics_doc)]
-    pub fn change_detection(self) -> Self {
+    pub fn change_detection(mut self) -> Self {
         use ::change_detection::{
             path_matchers::{any, equal, func, starts_with, PathMatcherExt},
             ChangeDetection,
@@ -126,32 +281,244 @@ impl NpmBuild {
 
             change_detection.path(&self.package_json_dir).generate();
         }
+        println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+        for directive in self.cargo_directives.drain(..) {
+            println!("cargo:{directive}");
+        }
+        self.change_detection_generated = true;
         self
     }
 
     /// Executes `npm install`.
-    pub fn install(mut self) -> io::Result<Self> {
-        self.package_command()
-            .arg("install")
-            .status()
-            .map_err(|err| {
-                eprintln!("Cannot execute {} install: {err:?}", self.executable);
-                err
-            })
-            .map(|_| self)
+    ///
+    /// Fails if the executable exits with an unsuccessful status, like [`NpmBuild::run`].
+    pub fn install(self) -> io::Result<Self> {
+        self.install_args(&[])
+    }
+
+    /// Executes `npm install` only if `node_modules` is missing or the lock file changed
+    /// since the last install.
+    ///
+    /// Hash of the lock file, like `package-lock.json` or `pnpm-lock.yaml`, is kept in a stamp
+    /// file in `OUT_DIR`. Without a lock file or `OUT_DIR` the install is always executed.
+    /// With [`NpmBuild::change_detection`] Cargo also reruns the build script on lock file changes.
+    ///
+    /// ```rust
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::{env, fs, path::Path};
+    /// use static_files::NpmBuild;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let out_dir = Path::new(&out_dir);
+    /// let package_dir = out_dir.join("npm_if_needed");
+    /// let _ = fs::remove_dir_all(&package_dir);
+    /// fs::create_dir_all(&package_dir).unwrap();
+    /// fs::write(package_dir.join("package-lock.json"), "{}").unwrap();
+    ///
+    /// // Fake package manager logging installs to `npm.log`.
+    /// let installs = || {
+    ///     NpmBuild::new(&package_dir)
+    ///         .executable(out_dir.join("fake_npm/npm").to_str().unwrap())
+    ///         .install_if_needed()
+    ///         .unwrap();
+    ///     fs::read_to_string(package_dir.join("npm.log")).unwrap().lines().count()
+    /// };
+    ///
+    /// assert_eq!(installs(), 1);
+    /// assert_eq!(installs(), 1);
+    ///
+    /// fs::write(package_dir.join("package-lock.json"), "{\"lockfileVersion\": 3}").unwrap();
+    /// assert_eq!(installs(), 2);
+    ///
+    /// fs::remove_dir_all(package_dir.join("node_modules")).unwrap();
+    /// assert_eq!(installs(), 3);
+    /// assert_eq!(installs(), 3);
+    /// # }
+    /// ```
+    #[cfg_attr(not(feature = "change-detection"), allow(unused_mut))]
+    pub fn install_if_needed(mut self) -> io::Result<Self> {
+        let lock_file = std::iter::once("package-lock.json")
+            .chain(LOCK_FILES.iter().map(|(lock_file, _)| *lock_file))
+            .map(|lock_file| self.package_json_dir.join(lock_file))
+            .find(|lock_file| lock_file.is_file());
+        let (lock_file, out_dir) = match (lock_file, env::var_os("OUT_DIR")) {
+            (Some(lock_file), Some(out_dir)) => (lock_file, PathBuf::from(out_dir)),
+            _ => return self.install(),
+        };
+
+        #[cfg(feature = "change-detection")]
+        self.cargo_directive(format!("rerun-if-changed={}", lock_file.display()));
+
+        let stamp_file = out_dir.join(format!(
+            "npm-install-{:016x}.stamp",
+            fnv1a_hash(self.package_json_dir.to_string_lossy().bytes())
+        ));
+        let stamp = format!("{:016x}", fnv1a_hash(fs::read(&lock_file)?.into_iter()));
+        let installed = self.package_json_dir.join("node_modules").is_dir()
+            && fs::read_to_string(&stamp_file).map_or(false, |previous| previous == stamp);
+        if installed {
+            return Ok(self);
+        }
+
+        let npm_build = self.install()?;
+        fs::write(stamp_file, stamp)?;
+        Ok(npm_build)
+    }
+
+    /// Executes `npm install` with additional arguments `args`, like `--no-audit`.
+    pub fn install_args(mut self, args: &[&str]) -> io::Result<Self> {
+        let args = [&["install"], args].concat();
+        let status = self.package_command()?.args(&args).status();
+        self.check_status(status, &args.join(" "))?;
+        Ok(self)
+    }
+
+    /// Executes clean install from the lock file, failing if it is out of date.
+    ///
+    /// Runs `npm ci`, or `install --frozen-lockfile` for `pnpm`, `yarn` and `bun`.
+    ///
+    /// ```rust
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::{env, fs, path::Path};
+    /// use static_files::NpmBuild;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let out_dir = Path::new(&out_dir);
+    /// let package_dir = out_dir.join("npm_ci");
+    /// let _ = fs::remove_dir_all(&package_dir);
+    /// fs::create_dir_all(&package_dir).unwrap();
+    ///
+    /// // Fake package managers logging their arguments to `npm.log`.
+    /// for executable in ["npm", "pnpm", "yarn"] {
+    ///     NpmBuild::new(&package_dir)
+    ///         .executable(out_dir.join("fake_npm").join(executable).to_str().unwrap())
+    ///         .ci()
+    ///         .unwrap();
+    /// }
+    ///
+    /// let log = fs::read_to_string(package_dir.join("npm.log")).unwrap();
+    /// assert_eq!(
+    ///     log,
+    ///     "npm ci\npnpm install --frozen-lockfile\nyarn install --frozen-lockfile\n"
+    /// );
+    /// # }
+    /// ```
+    pub fn ci(mut self) -> io::Result<Self> {
+        let executable = Path::new(&self.executable)
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .unwrap_or_default();
+        let args: &[&str] = match executable {
+            "pnpm" | "yarn" | "bun" => &["install", "--frozen-lockfile"],
+            _ => &["ci"],
+        };
+
+        let status = self.package_command()?.args(args).status();
+        self.check_status(status, &args.join(" "))?;
+        Ok(self)
     }
 
     /// Executes `npm run CMD`.
+    ///
+    /// Fails if the executable exits with an unsuccessful status.
+    ///
+    /// ```rust
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::{env, fs, path::Path};
+    /// use static_files::NpmBuild;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let out_dir = Path::new(&out_dir);
+    /// let package_dir = out_dir.join("npm_status");
+    /// fs::create_dir_all(&package_dir).unwrap();
+    ///
+    /// // Fake package manager exiting with status 3 for `fail` script.
+    /// let err = NpmBuild::new(&package_dir)
+    ///     .executable(out_dir.join("fake_npm/npm").to_str().unwrap())
+    ///     .run("fail")
+    ///     .unwrap_err();
+    /// assert!(err.to_string().contains("exit status: 3"));
+    /// # }
+    /// ```
     pub fn run(mut self, cmd: &str) -> io::Result<Self> {
-        self.package_command()
+        let status = self.package_command()?.arg("run").arg(cmd).status();
+        self.check_status(status, &format!("run {cmd}"))?;
+        Ok(self)
+    }
+
+    /// Executes `npm run CMD` for each of `cmds` in order, stopping at the first failed one.
+    ///
+    /// Stdout and stderr set with [`NpmBuild::stdout`] and [`NpmBuild::stderr`] are used by
+    /// the first script only, like with chained [`NpmBuild::run`] calls: `Stdio` is opaque,
+    /// so even a `File` converted to it can't be duplicated. Pass files to
+    /// [`NpmBuild::stdout_file`] and [`NpmBuild::stderr_file`] instead to redirect
+    /// the output of every script.
+    ///
+    /// ```rust
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::{env, fs, path::Path};
+    /// use static_files::NpmBuild;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let out_dir = Path::new(&out_dir);
+    /// let package_dir = out_dir.join("npm_run_all");
+    /// let _ = fs::remove_dir_all(&package_dir);
+    /// fs::create_dir_all(&package_dir).unwrap();
+    ///
+    /// // Fake package manager logging scripts to `npm.log`, `fail` script exits with an error.
+    /// let npm_build = || {
+    ///     NpmBuild::new(&package_dir).executable(out_dir.join("fake_npm/npm").to_str().unwrap())
+    /// };
+    /// npm_build().run_all(&["build", "postbuild"]).unwrap();
+    /// assert!(npm_build().run_all(&["lint", "fail", "deploy"]).is_err());
+    ///
+    /// let log = fs::read_to_string(package_dir.join("npm.log")).unwrap();
+    /// assert_eq!(log, "npm run build\nnpm run postbuild\nnpm run lint\nnpm run fail\n");
+    /// # }
+    /// ```
+    pub fn run_all(self, cmds: &[&str]) -> io::Result<Self> {
+        cmds.iter()
+            .try_fold(self, |npm_build, cmd| npm_build.run(cmd))
+    }
+
+    /// Executes `npm run CMD` and returns its captured output.
+    ///
+    /// Stdout and stderr set with [`NpmBuild::stdout`] and [`NpmBuild::stderr`] are kept
+    /// for the next command. Unlike [`NpmBuild::run`] the exit status is not checked,
+    /// it is returned in the output.
+    ///
+    /// ```rust
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::{env, fs, path::Path};
+    /// use static_files::NpmBuild;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let out_dir = Path::new(&out_dir);
+    /// let package_dir = out_dir.join("npm_capture");
+    /// fs::create_dir_all(&package_dir).unwrap();
+    ///
+    /// // Fake package manager printing its arguments.
+    /// let npm_build = NpmBuild::new(&package_dir)
+    ///     .executable(out_dir.join("fake_npm/npm").to_str().unwrap());
+    /// let output = npm_build.run_capture("build").unwrap();
+    /// assert!(output.status.success());
+    /// assert_eq!(output.stdout, b"run build\n");
+    /// # }
+    /// ```
+    pub fn run_capture(&self, cmd: &str) -> io::Result<Output> {
+        self.package_dir_command()
             .arg("run")
             .arg(cmd)
-            .status()
+            .output()
             .map_err(|err| {
                 eprintln!("Cannot execute {} run {cmd}: {err:?}", self.executable);
                 err
             })
-            .map(|_| self)
     }
 
     /// Sets target (default is `node_modules`).
@@ -161,9 +528,102 @@ impl NpmBuild {
         self
     }
 
+    /// Sets target from the environment variable `var_name`.
+    ///
+    /// Use it if the JS build exports its output directory, for example `VITE_OUT`.
+    /// The path is handled the same way as in [`NpmBuild::target`]. With
+    /// [`NpmBuild::change_detection`] Cargo reruns the build script when the variable changes.
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    /// use static_files::NpmBuild;
+    ///
+    /// env::set_var("STATIC_FILES_TARGET", "./tests");
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let mut resources = NpmBuild::new("./web")
+    ///     .target_from_env("STATIC_FILES_TARGET")
+    ///     .unwrap()
+    ///     .to_resource_dir();
+    /// resources
+    ///     .with_generated_filename(Path::new(&out_dir).join("generated_npm_env.rs"))
+    ///     .with_generated_fn("npm_env");
+    /// resources.build().unwrap();
+    ///
+    /// let set = fs::read_to_string(Path::new(&out_dir).join("npm_env_sets/set_1.rs")).unwrap();
+    /// assert!(set.contains("\"file1.txt\""));
+    ///
+    /// assert!(NpmBuild::new("./web").target_from_env("STATIC_FILES_UNDEFINED").is_err());
+    /// ```
+    #[cfg_attr(not(feature = "change-detection"), allow(unused_mut))]
+    pub fn target_from_env(mut self, var_name: &str) -> io::Result<Self> {
+        #[cfg(feature = "change-detection")]
+        self.cargo_directive(format!("rerun-if-env-changed={var_name}"));
+
+        let target_dir = env::var_os(var_name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("environment variable {var_name} is not set"),
+            )
+        })?;
+
+        Ok(self.target(target_dir))
+    }
+
+    /// Sets environment variable `key` for all following commands.
+    ///
+    /// Unlike [`NpmBuild::stderr`] and [`NpmBuild::stdout`], variables are kept for every
+    /// command, like `install` and `run` in a chain.
+    ///
+    /// ```rust
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::{env, fs, path::Path};
+    /// use static_files::NpmBuild;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let out_dir = Path::new(&out_dir);
+    /// let package_dir = out_dir.join("npm_env_vars");
+    /// let _ = fs::remove_dir_all(&package_dir);
+    /// fs::create_dir_all(&package_dir).unwrap();
+    ///
+    /// // Fake package manager logging `NODE_ENV` and `REGISTRY_TOKEN` to `env.log`.
+    /// NpmBuild::new(&package_dir)
+    ///     .executable(out_dir.join("fake_npm/npm").to_str().unwrap())
+    ///     .env("NODE_ENV", "production")
+    ///     .envs([("REGISTRY_TOKEN", "secret")])
+    ///     .install()
+    ///     .unwrap()
+    ///     .run("build")
+    ///     .unwrap();
+    ///
+    /// let log = fs::read_to_string(package_dir.join("env.log")).unwrap();
+    /// assert_eq!(log, "production secret\nproduction secret\n");
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn env<K: AsRef<OsStr>, V: AsRef<OsStr>>(mut self, key: K, value: V) -> Self {
+        self.envs
+            .push((key.as_ref().to_owned(), value.as_ref().to_owned()));
+        self
+    }
+
+    /// Sets environment variables `vars` for all following commands, see [`NpmBuild::env`].
+    #[must_use]
+    pub fn envs<I, K, V>(self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        vars.into_iter()
+            .fold(self, |npm_build, (key, value)| npm_build.env(key, value))
+    }
+
     /// Sets stderr for the next command.
     ///
     /// You should set it again, if you need also redirect output for the next command.
+    /// Use [`NpmBuild::stderr_file`] to redirect it for all following commands.
     #[must_use]
     pub fn stderr<S: Into<Stdio>>(mut self, stdio: S) -> Self {
         self.stderr = Some(stdio.into());
@@ -173,12 +633,57 @@ impl NpmBuild {
     /// Sets stdout for the next command.
     ///
     /// You should set it again, if you need also redirect output for the next command.
+    /// Use [`NpmBuild::stdout_file`] to redirect it for all following commands.
     #[must_use]
     pub fn stdout<S: Into<Stdio>>(mut self, stdio: S) -> Self {
         self.stdout = Some(stdio.into());
         self
     }
 
+    /// Redirects stderr of all following commands to `file`, see [`NpmBuild::stdout_file`].
+    #[must_use]
+    pub fn stderr_file(mut self, file: File) -> Self {
+        self.stderr_file = Some(file);
+        self
+    }
+
+    /// Redirects stdout of all following commands to `file`, like a build log.
+    ///
+    /// Every command writes to a duplicate of the file handle, so outputs of commands
+    /// follow each other. Stdout set with [`NpmBuild::stdout`] takes precedence for the next command.
+    ///
+    /// ```rust
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::{env, fs, fs::File, path::Path};
+    /// use static_files::NpmBuild;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let out_dir = Path::new(&out_dir);
+    /// let package_dir = out_dir.join("npm_stdout_file");
+    /// let _ = fs::remove_dir_all(&package_dir);
+    /// fs::create_dir_all(&package_dir).unwrap();
+    ///
+    /// // Fake package manager printing its arguments.
+    /// let log_path = package_dir.join("build.log");
+    /// NpmBuild::new(&package_dir)
+    ///     .executable(out_dir.join("fake_npm/npm").to_str().unwrap())
+    ///     .stdout_file(File::create(&log_path).unwrap())
+    ///     .install()
+    ///     .unwrap()
+    ///     .run_all(&["build", "postbuild"])
+    ///     .unwrap();
+    ///
+    /// let log = fs::read_to_string(&log_path).unwrap();
+    /// assert_eq!(log, "install\nrun build\nrun postbuild\n");
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn stdout_file(mut self, file: File) -> Self {
+        self.stdout_file = Some(file);
+        self
+    }
+
     /// Converts to `ResourceDir`.
     #[allow(clippy::wrong_self_convention)]
     #[must_use]
@@ -186,13 +691,51 @@ impl NpmBuild {
         self.into()
     }
 
+    /// Returns error if `status` of the command `args` failed or is unsuccessful.
+    fn check_status(&self, status: io::Result<ExitStatus>, args: &str) -> io::Result<()> {
+        let status = status.map_err(|err| {
+            eprintln!("Cannot execute {} {args}: {err:?}", self.executable);
+            err
+        })?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("{} {args} failed with {status}", self.executable),
+            ))
+        }
+    }
+
+    /// Prints Cargo `directive` with change detection instructions, or records it until
+    /// [`NpmBuild::change_detection`] is called, so it never disables Cargo's default
+    /// change detection of the package on its own.
+    #[cfg(feature = "change-detection")]
+    fn cargo_directive(&mut self, directive: String) {
+        if self.change_detection_generated {
+            println!("cargo:{directive}");
+        } else {
+            self.cargo_directives.push(directive);
+        }
+    }
+
     #[cfg(not(windows))]
     fn command(&self) -> Command {
         Command::new(&self.executable)
     }
 
+    /// Runs batch scripts like `npm.cmd` through `cmd`, and executables like `bun.exe` directly,
+    /// so their exit status is not lost.
     #[cfg(windows)]
     fn command(&self) -> Command {
+        let is_exe = Path::new(&self.executable)
+            .extension()
+            .map_or(false, |extension| extension.eq_ignore_ascii_case("exe"));
+        if is_exe {
+            return Command::new(&self.executable);
+        }
+
         let mut cmd = Command::new("cmd");
 
         cmd.arg("/c").arg(&self.executable);
@@ -200,17 +743,34 @@ impl NpmBuild {
         cmd
     }
 
-    fn package_command(&mut self) -> Command {
+    fn package_command(&mut self) -> io::Result<Command> {
+        let mut cmd = self.package_dir_command();
+
+        cmd.stderr(take_stdio(&mut self.stderr, self.stderr_file.as_ref())?)
+            .stdout(take_stdio(&mut self.stdout, self.stdout_file.as_ref())?);
+
+        Ok(cmd)
+    }
+
+    fn package_dir_command(&self) -> Command {
         let mut cmd = self.command();
 
-        cmd.stderr(self.stderr.take().unwrap_or_else(Stdio::inherit))
-            .stdout(self.stdout.take().unwrap_or_else(Stdio::inherit))
-            .current_dir(&self.package_json_dir);
+        cmd.current_dir(&self.package_json_dir)
+            .envs(self.envs.iter().map(|(key, value)| (key, value)));
 
         cmd
     }
 }
 
+/// Returns `stdio` of the next command, or a duplicate of `file` of all commands, or inherited one.
+fn take_stdio(stdio: &mut Option<Stdio>, file: Option<&File>) -> io::Result<Stdio> {
+    match (stdio.take(), file) {
+        (Some(stdio), _) => Ok(stdio),
+        (None, Some(file)) => Ok(file.try_clone()?.into()),
+        (None, None) => Ok(Stdio::inherit()),
+    }
+}
+
 impl From<NpmBuild> for ResourceDir {
     fn from(mut npm_build: NpmBuild) -> Self {
         Self {
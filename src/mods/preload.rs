@@ -0,0 +1,112 @@
+/*!
+Preload hints extraction from HTML.
+ */
+
+/// Returns `(href, destination)` of resources referenced by `html`
+/// with `<script src>`, `<link rel=stylesheet>` and `<link rel=preload>` tags.
+///
+/// Destination is the value of the `as` attribute of `Link` preload header.
+pub(crate) fn preload_hints(html: &str) -> Vec<(String, String)> {
+    let mut hints: Vec<(String, String)> = vec![];
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+
+        if let Some(comment) = rest.strip_prefix("!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+
+        let end = rest.find('>').unwrap_or(rest.len());
+        let tag = &rest[..end];
+        rest = &rest[end..];
+
+        let name_end = tag
+            .find(|c: char| c.is_ascii_whitespace() || c == '/')
+            .unwrap_or(tag.len());
+        let name = tag[..name_end].to_ascii_lowercase();
+        let attributes = parse_attributes(&tag[name_end..]);
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(attribute, _)| attribute == name)
+                .map(|(_, value)| value.as_str())
+        };
+
+        let hint = match name.as_str() {
+            "script" => {
+                let script_end = rest
+                    .to_ascii_lowercase()
+                    .find("</script")
+                    .unwrap_or(rest.len());
+                rest = &rest[script_end..];
+
+                attribute("src").map(|src| (src, "script"))
+            }
+            "link" => {
+                let rel = attribute("rel").unwrap_or_default().to_ascii_lowercase();
+                let rel = rel.split_ascii_whitespace().collect::<Vec<_>>();
+
+                if rel.contains(&"stylesheet") {
+                    attribute("href").map(|href| (href, "style"))
+                } else if rel.contains(&"modulepreload") {
+                    attribute("href").map(|href| (href, "script"))
+                } else if rel.contains(&"preload") {
+                    attribute("href").map(|href| (href, attribute("as").unwrap_or_default()))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+
+        if let Some((href, destination)) = hint {
+            if !href.is_empty() && !hints.iter().any(|(hint_href, _)| hint_href == href) {
+                hints.push((href.into(), destination.into()));
+            }
+        }
+    }
+
+    hints
+}
+
+fn parse_attributes(attributes: &str) -> Vec<(String, String)> {
+    let mut result = vec![];
+    let mut rest = attributes;
+
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == '/');
+        if rest.is_empty() {
+            break;
+        }
+
+        let name_end = rest
+            .find(|c: char| c.is_ascii_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        let value = if let Some(value) = rest.strip_prefix('=') {
+            let value = value.trim_start();
+            let (value, value_rest) = if let Some(quote @ ('"' | '\'')) = value.chars().next() {
+                let value = &value[1..];
+                let end = value.find(quote).unwrap_or(value.len());
+                (&value[..end], value.get(end + 1..).unwrap_or_default())
+            } else {
+                let end = value
+                    .find(|c: char| c.is_ascii_whitespace())
+                    .unwrap_or(value.len());
+                (&value[..end], &value[end..])
+            };
+            rest = value_rest;
+            value.to_owned()
+        } else {
+            String::new()
+        };
+
+        result.push((name, value));
+    }
+
+    result
+}
@@ -1,145 +1,1897 @@
 /*!
 Resource definition and single module based generation.
  */
+#[cfg(feature = "std")]
 use std::{
+    borrow::{Borrow, Cow},
+    collections::{hash_map::RandomState, BTreeMap, HashMap},
     fs::{self, File, Metadata},
+    hash::BuildHasher,
     io::{self, Write},
-    path::{Path, PathBuf},
+    ops::Bound,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
     time::SystemTime,
 };
 
+#[cfg(feature = "std")]
+use mime_guess::{mime, Mime};
+#[cfg(feature = "std")]
 use path_slash::PathExt;
 
+#[cfg(feature = "std")]
+use super::preload::preload_hints;
+
 /// Static files resource.
+///
+/// Empty files are embedded as resources with empty data, so a missing key
+/// always means the file was not embedded.
+///
+/// ```rust
+/// use std::collections::HashMap;
+///
+/// use static_files::Resource;
+///
+/// fn generate_mapping() -> HashMap<&'static str, Resource> {
+///   include!(concat!(env!("OUT_DIR"), "/generated_mapping.rs"))
+/// }
+///
+/// let resources = generate_mapping();
+///
+/// assert!(resources.contains_key("file2.txt"));
+/// assert!(resources["file2.txt"].data.is_empty());
+/// assert!(!resources.contains_key("missing.txt"));
+/// ```
+///
+/// Resources are `Send + Sync`, so generated resources can be stored in a global:
+///
+/// ```rust
+/// use static_files::Resource;
+///
+/// fn assert_send_sync<T: Send + Sync>() {}
+/// assert_send_sync::<Resource>();
+/// ```
+///
+/// Fields are added as new attributes are computed at build time, so resources can't be built
+/// with a struct literal outside of this crate. Use [`new_resource`] and `with_*` methods instead.
+#[non_exhaustive]
 pub struct Resource {
     pub data: &'static [u8],
     pub modified: u64,
     pub mime_type: &'static str,
+    /// Intrinsic `(width, height)` of image resources.
+    ///
+    /// Computed at build time with `image` feature, `None` for other resources.
+    pub dimensions: Option<(u32, u32)>,
+    /// Whether `data` was valid UTF-8 text at build time.
+    text: bool,
+    /// Resources referenced by HTML resource to preload.
+    ///
+    /// Collected at build time with [`ResourceDir::with_preload_hints`](crate::ResourceDir::with_preload_hints),
+    /// empty for other resources.
+    pub preload: &'static [PreloadHint],
+    /// Extended attributes of the resource file.
+    ///
+    /// Captured at build time with [`ResourceDir::with_xattrs`](crate::ResourceDir::with_xattrs),
+    /// empty for platforms without extended attributes.
+    pub xattrs: &'static [Xattr],
+    /// Suggested file name to download the resource with, e.g. in `Content-Disposition` header.
+    ///
+    /// Base name of the resource file with [`ResourceDir::with_download_names`](crate::ResourceDir::with_download_names),
+    /// `None` otherwise.
+    pub download_name: Option<&'static str>,
+    /// Value of `Vary` header: `Accept-Encoding` for resources with precompressed variants,
+    /// `Accept` for images with variants in other formats.
+    ///
+    /// Computed at build time with [`ResourceDir::with_vary_hints`](crate::ResourceDir::with_vary_hints),
+    /// `None` otherwise.
+    pub vary: Option<&'static str>,
+    /// Position of the resource in the sequence of resources sorted by key.
+    ///
+    /// Stored with [`ResourceDir::with_order`](crate::ResourceDir::with_order), `0` otherwise.
+    pub order: u32,
+    /// Whether the resource never changes under its key, e.g. has a content hash in its file name.
+    /// Use it to emit `Cache-Control: immutable` header.
+    ///
+    /// Detected at build time with [`ResourceDir::with_immutable`](crate::ResourceDir::with_immutable),
+    /// `false` otherwise.
+    pub immutable: bool,
+    /// Hex SHA-256 of the data, use it quoted as value of `ETag` header.
+    ///
+    /// Computed at build time with `ResourceDir::with_etags` of `integrity` feature, `None` otherwise.
+    pub etag: Option<&'static str>,
+    /// Subresource Integrity metadata, like `sha384-<base64>`, use it as value of `integrity` attribute.
+    ///
+    /// Computed at build time with `ResourceDir::with_sri` of `integrity` feature, `None` otherwise.
+    pub integrity: Option<&'static str>,
+    /// Unix permission bits of the resource file, like `0o755` for executables.
+    ///
+    /// Captured at build time on Unix with [`ResourceDir::with_modes`](crate::ResourceDir::with_modes),
+    /// `None` otherwise.
+    pub mode: Option<u32>,
+}
+
+/// Resource referenced by HTML resource, use it to emit `Link: <href>; rel=preload; as=destination` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PreloadHint {
+    /// Reference as written in HTML.
+    pub href: &'static str,
+    /// Value of `as` attribute: `script`, `style` or as specified in `<link rel=preload>`.
+    pub destination: &'static str,
+}
+
+/// Extended attribute of the resource file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Xattr {
+    /// Attribute name, e.g. `user.mime_type`.
+    pub name: &'static str,
+    pub value: &'static [u8],
+}
+
+// Fails to compile if a field makes resources unsafe to share between threads.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Resource>();
+};
+
+impl Resource {
+    /// Returns the content of text resources.
+    ///
+    /// Text resources are detected by mime type at build time, `None` for other resources
+    /// and if `data` was replaced with invalid UTF-8.
+    ///
+    /// ```rust
+    /// use std::{collections::HashMap, fs};
+    ///
+    /// use static_files::Resource;
+    ///
+    /// fn generate_mapping() -> HashMap<&'static str, Resource> {
+    ///   include!(concat!(env!("OUT_DIR"), "/generated_mapping.rs"))
+    /// }
+    ///
+    /// let resources = generate_mapping();
+    ///
+    /// let text = fs::read_to_string("./tests/file1.txt").unwrap();
+    /// assert_eq!(resources["file1.txt"].as_str(), Some(text.as_str()));
+    /// assert_eq!(resources["file3.info"].as_str(), None);
+    ///
+    /// let mut resource = generate_mapping().remove("file1.txt").unwrap();
+    /// resource.data = b"\xff";
+    /// assert_eq!(resource.as_str(), None);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn as_str(&self) -> Option<&'static str> {
+        if self.text {
+            core::str::from_utf8(self.data).ok()
+        } else {
+            None
+        }
+    }
+
+    /// Used internally in generated functions.
+    #[inline]
+    #[must_use]
+    pub const fn with_dimensions(mut self, width: u32, height: u32) -> Self {
+        self.dimensions = Some((width, height));
+        self
+    }
+
+    /// Used internally in generated functions.
+    #[inline]
+    #[must_use]
+    pub const fn with_preload(mut self, preload: &'static [PreloadHint]) -> Self {
+        self.preload = preload;
+        self
+    }
+
+    /// Used internally in generated functions.
+    #[inline]
+    #[must_use]
+    pub const fn with_xattrs(mut self, xattrs: &'static [Xattr]) -> Self {
+        self.xattrs = xattrs;
+        self
+    }
+
+    /// Used internally in generated functions.
+    #[inline]
+    #[must_use]
+    pub const fn with_download_name(mut self, download_name: &'static str) -> Self {
+        self.download_name = Some(download_name);
+        self
+    }
+
+    /// Used internally in generated functions.
+    #[inline]
+    #[must_use]
+    pub const fn with_vary(mut self, vary: &'static str) -> Self {
+        self.vary = Some(vary);
+        self
+    }
+
+    /// Used internally in generated functions.
+    #[inline]
+    #[must_use]
+    pub const fn with_order(mut self, order: u32) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Used internally in generated functions.
+    #[inline]
+    #[must_use]
+    pub const fn with_immutable(mut self) -> Self {
+        self.immutable = true;
+        self
+    }
+
+    /// Used internally in generated functions.
+    #[inline]
+    #[must_use]
+    pub const fn with_etag(mut self, etag: &'static str) -> Self {
+        self.etag = Some(etag);
+        self
+    }
+
+    /// Used internally in generated functions.
+    #[inline]
+    #[must_use]
+    pub const fn with_integrity(mut self, integrity: &'static str) -> Self {
+        self.integrity = Some(integrity);
+        self
+    }
+
+    /// Used internally in generated functions.
+    #[inline]
+    #[must_use]
+    pub const fn with_mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Sets captured extended attributes to the file `path`, e.g. after extracting the resource.
+    ///
+    /// Does nothing on platforms without extended attributes.
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    ///
+    /// # #[cfg(unix)]
+    /// include!(concat!(env!("OUT_DIR"), "/generated_xattr.rs"));
+    ///
+    /// fn main() {
+    /// #   #[cfg(unix)]
+    /// #   {
+    ///     let resources = generate_xattr();
+    ///     let resource = &resources["file.txt"];
+    ///
+    ///     let path = Path::new(&env::var("OUT_DIR").unwrap()).join("xattr_extracted.txt");
+    ///     fs::write(&path, resource.data).unwrap();
+    ///     resource.restore_xattrs(&path).unwrap();
+    ///
+    ///     let value = xattr::get(&path, "user.static-files").unwrap();
+    ///     assert_eq!(value.as_deref(), Some(&b"embedded"[..]));
+    /// #   }
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if an attribute can't be set.
+    #[cfg(feature = "xattr")]
+    pub fn restore_xattrs<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        #[cfg(unix)]
+        for attribute in self.xattrs {
+            xattr::set(path.as_ref(), attribute.name, attribute.value)?;
+        }
+        #[cfg(not(unix))]
+        let _ = path;
+
+        Ok(())
+    }
 }
 
 /// Used internally in generated functions.
 #[inline]
 #[must_use]
-pub fn new_resource(data: &'static [u8], modified: u64, mime_type: &'static str) -> Resource {
+pub const fn new_resource(data: &'static [u8], modified: u64, mime_type: &'static str) -> Resource {
     Resource {
         data,
         modified,
         mime_type,
+        dimensions: None,
+        text: false,
+        preload: &[],
+        xattrs: &[],
+        download_name: None,
+        vary: None,
+        order: 0,
+        immutable: false,
+        etag: None,
+        integrity: None,
+        mode: None,
+    }
+}
+
+/// Used internally in generated functions.
+#[inline]
+#[must_use]
+pub const fn new_text_resource(
+    text: &'static str,
+    modified: u64,
+    mime_type: &'static str,
+) -> Resource {
+    Resource {
+        data: text.as_bytes(),
+        modified,
+        mime_type,
+        dimensions: None,
+        text: true,
+        preload: &[],
+        xattrs: &[],
+        download_name: None,
+        vary: None,
+        order: 0,
+        immutable: false,
+        etag: None,
+        integrity: None,
+        mode: None,
+    }
+}
+
+/// Returns resources with keys matching glob `pattern`.
+///
+/// `?` matches any character except `/`, `*` matches any characters except `/`,
+/// `**` matches any characters, `**/` at the start of a segment matches any number of directories.
+/// Other characters match themselves. Character classes (`[...]`), alternatives (`{...}`)
+/// and escapes (`\`) are not supported.
+/// Resources are checked one by one, iteration order is the order of `resources`.
+///
+/// ```rust
+/// use std::collections::HashMap;
+///
+/// use static_files::{resource::glob, Resource};
+///
+/// fn generate_mapping() -> HashMap<&'static str, Resource> {
+///   include!(concat!(env!("OUT_DIR"), "/generated_mapping.rs"))
+/// }
+///
+/// let resources = generate_mapping();
+///
+/// let mut keys = glob(&resources, "file?.*").map(|(key, _)| key).collect::<Vec<_>>();
+/// keys.sort_unstable();
+/// assert_eq!(keys, ["file1.txt", "file2.txt", "file3.info"]);
+///
+/// let keys = glob(&resources, "**/*.html").map(|(key, _)| key).collect::<Vec<_>>();
+/// assert_eq!(keys, ["index.html"]);
+///
+/// assert_eq!(glob(&resources, "*.js").count(), 0);
+///
+/// // Matching time doesn't grow exponentially with the count of `*`.
+/// let key = "a".repeat(1000);
+/// let resource = static_files::resource::new_resource(b"", 0, "text/plain");
+/// let pattern = "*a".repeat(50) + "b";
+/// assert_eq!(glob([(key.as_str(), &resource)], &pattern).count(), 0);
+///
+/// assert!(std::panic::catch_unwind(|| glob(&resources, "*.{js,css}").count()).is_err());
+/// ```
+///
+/// # Panics
+/// Panics if `pattern` contains `[`, `{` or `\`.
+pub fn glob<'a, K, I>(
+    resources: I,
+    pattern: &'a str,
+) -> impl Iterator<Item = (&'a str, &'a Resource)>
+where
+    K: AsRef<str> + ?Sized + 'a,
+    I: IntoIterator<Item = (&'a K, &'a Resource)>,
+{
+    assert!(
+        !pattern.contains(&UNSUPPORTED_GLOB_CHARS[..]),
+        "glob pattern {pattern:?} contains unsupported syntax"
+    );
+    resources
+        .into_iter()
+        .map(|(key, resource)| (key.as_ref(), resource))
+        .filter(move |(key, _)| glob_match(pattern, key))
+}
+
+/// Characters of glob syntax not supported by [`glob`], rejected instead of matched literally.
+pub(crate) const UNSUPPORTED_GLOB_CHARS: [char; 3] = ['[', '{', '\\'];
+
+/// Returns whether `key` matches glob `pattern`.
+///
+/// Runs in `O(pattern.len() * key.len())`: on a mismatch only the last `*`
+/// of the current segment and the last `**` are retried. Retrying an earlier
+/// `*` can't help, as it can't cross the `/` which ends its segment, and
+/// an earlier `**` matches fewer keys than the last one starting later,
+/// which is why `**/` only matches directories at the start of a segment.
+pub(crate) fn glob_match(pattern: &str, key: &str) -> bool {
+    let (pattern, key) = (pattern.as_bytes(), key.as_bytes());
+    let (mut pattern_index, mut key_index) = (0, 0);
+    // Pattern index after the last `*` and key index it's retried at.
+    let mut star = None;
+    // Pattern index after the last `**`, key index it's retried at and whether it's `**/`.
+    let mut globstar = None;
+
+    while pattern_index < pattern.len() || key_index < key.len() {
+        match pattern.get(pattern_index) {
+            Some(b'*') if pattern.get(pattern_index + 1) == Some(&b'*') => {
+                let directories = pattern.get(pattern_index + 2) == Some(&b'/')
+                    && (pattern_index == 0 || pattern[pattern_index - 1] == b'/');
+                pattern_index += if directories { 3 } else { 2 };
+                globstar = Some((pattern_index, key_index, directories));
+                star = None;
+                continue;
+            }
+            Some(b'*') => {
+                pattern_index += 1;
+                star = Some((pattern_index, key_index));
+                continue;
+            }
+            Some(b'?')
+                if key_index < key.len()
+                    && key[key_index] != b'/'
+                    && key[key_index] & 0xC0 != 0x80 =>
+            {
+                pattern_index += 1;
+                key_index += 1;
+                // `?` matches a whole character.
+                while key_index < key.len() && key[key_index] & 0xC0 == 0x80 {
+                    key_index += 1;
+                }
+                continue;
+            }
+            Some(&c) if c != b'?' && key.get(key_index) == Some(&c) => {
+                pattern_index += 1;
+                key_index += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        if let Some((star_pattern_index, star_key_index)) = star {
+            if star_key_index < key.len() && key[star_key_index] != b'/' {
+                star = Some((star_pattern_index, star_key_index + 1));
+                pattern_index = star_pattern_index;
+                key_index = star_key_index + 1;
+                continue;
+            }
+        }
+        if let Some((globstar_pattern_index, globstar_key_index, directories)) = globstar {
+            let next_key_index = if directories {
+                key[globstar_key_index..]
+                    .iter()
+                    .position(|&c| c == b'/')
+                    .map(|index| globstar_key_index + index + 1)
+            } else if globstar_key_index < key.len() {
+                Some(globstar_key_index + 1)
+            } else {
+                None
+            };
+            if let Some(next_key_index) = next_key_index {
+                globstar = Some((globstar_pattern_index, next_key_index, directories));
+                star = None;
+                pattern_index = globstar_pattern_index;
+                key_index = next_key_index;
+                continue;
+            }
+        }
+        return false;
+    }
+
+    true
+}
+
+/// Returns resources of sorted `resources` with keys starting with `prefix`, in key order.
+///
+/// Only the matching range of the map is visited, so it's efficient for directory subtrees.
+/// End `prefix` with `/` to match a directory and not siblings sharing the name start.
+///
+/// ```rust
+/// include!(concat!(env!("OUT_DIR"), "/generated_site.rs"));
+///
+/// fn main() {
+///     use std::collections::BTreeMap;
+///
+///     use static_files::resource::range_prefix;
+///
+///     let resources = generate_site().into_iter().collect::<BTreeMap<_, _>>();
+///
+///     let keys = range_prefix(&resources, "guide/")
+///         .map(|(key, _)| *key)
+///         .collect::<Vec<_>>();
+///     assert_eq!(keys, ["guide/index.html", "guide/intro.html"]);
+///
+///     assert_eq!(range_prefix(&resources, "").count(), resources.len());
+///     assert_eq!(range_prefix(&resources, "assets/").count(), 0);
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub fn range_prefix<'a, K>(
+    resources: &'a BTreeMap<K, Resource>,
+    prefix: &'a str,
+) -> impl Iterator<Item = (&'a K, &'a Resource)>
+where
+    K: Borrow<str> + Ord,
+{
+    resources
+        .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+        .take_while(move |(key, _)| (*key).borrow().starts_with(prefix))
+}
+
+/// Returns the resource for request `path` with single page application fallbacks.
+///
+/// Returns the resource with the exact key, the `entry` resource (usually `index.html`)
+/// for unmatched routes if present, the `not_found` resource otherwise, or `None` if none exists.
+/// Unmatched paths with a file extension in the last segment, like missing assets `/app.js`,
+/// are not routes, so they resolve to `not_found` instead of the `entry`.
+///
+/// ```rust
+/// use std::collections::HashMap;
+///
+/// use static_files::{resource::resolve_or_index, Resource};
+///
+/// fn generate_mapping() -> HashMap<&'static str, Resource> {
+///   include!(concat!(env!("OUT_DIR"), "/generated_mapping.rs"))
+/// }
+///
+/// let resources = generate_mapping();
+/// let resolve = |path, entry, not_found| {
+///     resolve_or_index(&resources, path, entry, not_found).map(|resource| resource.mime_type)
+/// };
+///
+/// assert_eq!(resolve("/file1.txt", "index.html", "404.html"), Some("text/plain"));
+/// assert_eq!(resolve("/users/42", "index.html", "404.html"), Some("text/html"));
+/// assert_eq!(resolve("/", "index.html", "404.html"), Some("text/html"));
+/// assert_eq!(resolve("/users/42", "app.html", "file3.info"), Some("application/octet-stream"));
+/// assert_eq!(resolve("/users/42", "app.html", "404.html"), None);
+/// assert_eq!(resolve("/missing.js", "index.html", "file3.info"), Some("application/octet-stream"));
+/// assert_eq!(resolve("/missing.js", "index.html", "404.html"), None);
+/// assert_eq!(resolve("/v1.2/users", "index.html", "404.html"), Some("text/html"));
+/// ```
+#[cfg(feature = "std")]
+pub fn resolve_or_index<'a, S: BuildHasher>(
+    resources: &'a HashMap<&'static str, Resource, S>,
+    path: &str,
+    entry: &str,
+    not_found: &str,
+) -> Option<&'a Resource> {
+    let key = path.trim_start_matches('/');
+    let is_route = !key
+        .rsplit('/')
+        .next()
+        .map_or(false, |name| name.contains('.'));
+    resources
+        .get(key)
+        .or_else(|| resources.get(entry).filter(|_| is_route))
+        .or_else(|| resources.get(not_found))
+}
+
+/// Returns the resource for request `path`, resolving directory paths to their `index` documents.
+///
+/// Paths ending with `/` resolve to the `index` resource (usually `index.html`) of that
+/// directory, other paths are matched exactly. Unlike [`resolve_or_index`] each directory
+/// has its own index, and unmatched paths return `None`.
+///
+/// ```rust
+/// include!(concat!(env!("OUT_DIR"), "/generated_site.rs"));
+///
+/// fn main() {
+///     use static_files::resource::resolve_dir_index;
+///
+///     let resources = generate_site();
+///     let resolve = |path| {
+///         resolve_dir_index(&resources, path, "index.html").map(|resource| resource.data)
+///     };
+///
+///     assert_eq!(resolve("/guide/"), Some(&b"guide"[..]));
+///     assert_eq!(resolve("/guide/"), resolve("/guide/index.html"));
+///     assert_eq!(resolve("/"), Some(&b"root"[..]));
+///     assert_eq!(resolve("/guide/intro.html"), Some(&b"intro"[..]));
+///     assert_eq!(resolve("/guide"), None);
+///     assert_eq!(resolve("/api/"), None);
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub fn resolve_dir_index<'a, S: BuildHasher>(
+    resources: &'a HashMap<&'static str, Resource, S>,
+    path: &str,
+    index: &str,
+) -> Option<&'a Resource> {
+    let key = path.trim_start_matches('/');
+    if key.is_empty() || key.ends_with('/') {
+        resources.get(format!("{key}{index}").as_str())
+    } else {
+        resources.get(key)
+    }
+}
+
+/// Returns `true` if `file_name` has a content hash segment before the extension,
+/// like `app.abc123.js` or `chunk-5f3e2a91.css`, as emitted by bundlers.
+///
+/// Hash segment is at least 6 ASCII alphanumeric characters with at least one digit,
+/// separated by `.` or `-`, which matches hex and base62 hashes of common bundlers.
+/// Use it with [`ResourceDir::with_immutable`](crate::ResourceDir::with_immutable),
+/// or pass a function with your own rule there instead.
+///
+/// ```rust
+/// use static_files::resource::is_hashed_file_name;
+///
+/// assert!(is_hashed_file_name("app.abc123.js"));
+/// assert!(is_hashed_file_name("chunk-5f3e2a91.css"));
+/// assert!(!is_hashed_file_name("index.html"));
+/// assert!(!is_hashed_file_name("jquery.min.js"));
+/// assert!(!is_hashed_file_name("app.min.js"));
+/// assert!(!is_hashed_file_name("bundle.module.js"));
+/// assert!(!is_hashed_file_name("abc123.js"));
+/// ```
+#[cfg(feature = "std")]
+#[must_use]
+pub fn is_hashed_file_name(file_name: &str) -> bool {
+    let stem = match file_name.rsplit_once('.') {
+        Some((stem, _)) => stem,
+        None => return false,
+    };
+    match stem.rsplit_once(['.', '-']) {
+        Some((name, hash)) => {
+            !name.is_empty()
+                && hash.len() >= 6
+                && hash.chars().all(|c| c.is_ascii_alphanumeric())
+                && hash.chars().any(|c| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+/// Entry of a virtual directory listed by [`list_dir`].
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirEntry<'a> {
+    /// Name of the file or directory in the listed directory.
+    pub name: &'a str,
+    pub is_dir: bool,
+    /// Data size of the file, or total data size of files in the directory.
+    pub size: usize,
+}
+
+/// Returns entries of virtual directory `prefix` reconstructed from keys of `resources`,
+/// sorted by name.
+///
+/// Leading and trailing `/` of `prefix` are ignored, empty `prefix` lists the root.
+///
+/// ```rust
+/// use std::collections::HashMap;
+///
+/// use static_files::resource::{list_dir, new_resource, DirEntry};
+///
+/// let resources = [
+///     ("index.html", &b"<html></html>"[..]),
+///     ("assets/app.js", b"app()"),
+///     ("assets/css/app.css", b"body{}"),
+///     ("assets/css/print.css", b"@media print{}"),
+/// ]
+/// .into_iter()
+/// .map(|(key, data)| (key, new_resource(data, 0, "")))
+/// .collect::<HashMap<_, _>>();
+///
+/// let entry = |name, is_dir, size| DirEntry { name, is_dir, size };
+/// assert_eq!(
+///     list_dir(&resources, "/"),
+///     [entry("assets", true, 25), entry("index.html", false, 13)]
+/// );
+/// assert_eq!(
+///     list_dir(&resources, "/assets/"),
+///     [entry("app.js", false, 5), entry("css", true, 20)]
+/// );
+/// assert_eq!(
+///     list_dir(&resources, "assets/css"),
+///     [entry("app.css", false, 6), entry("print.css", false, 14)]
+/// );
+/// assert!(list_dir(&resources, "asset").is_empty());
+/// ```
+#[cfg(feature = "std")]
+pub fn list_dir<'a, K, I>(resources: I, prefix: &str) -> Vec<DirEntry<'a>>
+where
+    K: AsRef<str> + ?Sized + 'a,
+    I: IntoIterator<Item = (&'a K, &'a Resource)>,
+{
+    let prefix = prefix.trim_matches('/');
+    let mut entries = BTreeMap::new();
+
+    for (key, resource) in resources {
+        let key = key.as_ref();
+        let path = if prefix.is_empty() {
+            Some(key)
+        } else {
+            key.strip_prefix(prefix)
+                .and_then(|path| path.strip_prefix('/'))
+        };
+        let path = if let Some(path) = path {
+            path
+        } else {
+            continue;
+        };
+        let entry = match path.split_once('/') {
+            Some((name, _)) => (name, true),
+            None => (path, false),
+        };
+        *entries.entry(entry).or_insert(0) += resource.data.len();
+    }
+
+    entries
+        .into_iter()
+        .map(|((name, is_dir), size)| DirEntry { name, is_dir, size })
+        .collect()
+}
+
+/// Returns `true` if data of `resources` matches `manifest_hash` computed at build time,
+/// see [`ResourceDir::with_manifest_hash`](crate::ResourceDir::with_manifest_hash).
+///
+/// Use it at startup to detect corrupted or modified resources. The hash is embedded
+/// next to the resources, so it doesn't protect against tampering with both.
+///
+/// ```rust
+/// # #[cfg(feature = "integrity")]
+/// include!(concat!(env!("OUT_DIR"), "/generated_integrity.rs"));
+///
+/// fn main() {
+/// #   #[cfg(feature = "integrity")]
+/// #   {
+///     use static_files::resource::verify_integrity;
+///
+///     let mut resources = generate_integrity();
+///     assert!(verify_integrity(&resources, MANIFEST_HASH));
+///
+///     resources.get_mut("file1.txt").unwrap().data = b"tampered";
+///     assert!(!verify_integrity(&resources, MANIFEST_HASH));
+/// #   }
+/// }
+/// ```
+#[cfg(feature = "integrity")]
+#[must_use]
+pub fn verify_integrity<S: BuildHasher>(
+    resources: &HashMap<&'static str, Resource, S>,
+    manifest_hash: &str,
+) -> bool {
+    let mut entries = resources
+        .iter()
+        .map(|(key, resource)| (*key, resource.data))
+        .collect::<Vec<_>>();
+    entries.sort_unstable_by_key(|(key, _)| *key);
+    self::manifest_hash(entries) == manifest_hash
+}
+
+/// Inserts `resources` into `router` under routes `/<key>`.
+///
+/// Braces of keys are escaped, so keys are matched literally, while other routes of
+/// the router can have parameters. Generated functions can be registered with
+/// [`ResourceDir::with_router_fn`](crate::ResourceDir::with_router_fn).
+///
+/// ```rust
+/// # #[cfg(feature = "matchit")]
+/// # {
+/// use std::collections::HashMap;
+///
+/// use static_files::{matchit::Router, resource::insert_routes, Resource};
+///
+/// fn generate_mapping() -> HashMap<&'static str, Resource> {
+///   include!(concat!(env!("OUT_DIR"), "/generated_mapping.rs"))
+/// }
+///
+/// let mut router = Router::new();
+/// insert_routes(&mut router, generate_mapping()).unwrap();
+/// insert_routes(&mut router, [("{user}.txt", static_files::resource::new_resource(b"", 0, "text/plain"))])
+///     .unwrap();
+///
+/// assert_eq!(router.at("/index.html").unwrap().value.mime_type, "text/html");
+/// assert!(router.at("/{user}.txt").is_ok());
+/// assert!(router.at("/admin.txt").is_err());
+/// # }
+/// ```
+///
+/// # Errors
+/// Returns an error if a route conflicts with a route of `router`.
+#[cfg(feature = "matchit")]
+pub fn insert_routes<K, I>(
+    router: &mut matchit::Router<Resource>,
+    resources: I,
+) -> Result<(), matchit::InsertError>
+where
+    K: AsRef<str>,
+    I: IntoIterator<Item = (K, Resource)>,
+{
+    for (key, resource) in resources {
+        let route = key.as_ref().replace('{', "{{").replace('}', "}}");
+        router.insert(format!("/{route}"), resource)?;
+    }
+    Ok(())
+}
+
+/// Writes `resources` to files `dest_dir/<key>`, creating directories.
+///
+/// Modification times of files are set to [`Resource::modified`], captured extended attributes
+/// are restored with `xattr` feature, and permissions to [`Resource::mode`] on Unix. Files with the size and modification time of the resource,
+/// or its [`Resource::etag`] with `integrity` feature, are up-to-date and their content is not written again,
+/// but their modification time, extended attributes and permissions are still restored.
+///
+/// ```rust
+/// # #[cfg(feature = "extract")]
+/// # {
+/// use std::{collections::HashMap, env, fs, path::Path, time::UNIX_EPOCH};
+///
+/// use static_files::{resource::extract_all, Resource};
+///
+/// fn generate_mapping() -> HashMap<&'static str, Resource> {
+///   include!(concat!(env!("OUT_DIR"), "/generated_mapping.rs"))
+/// }
+///
+/// let resources = generate_mapping();
+/// let dest_dir = Path::new(&env::var("OUT_DIR").unwrap()).join("extracted");
+/// extract_all(&resources, &dest_dir).unwrap();
+/// extract_all(&resources, &dest_dir).unwrap();
+///
+/// for (key, resource) in &resources {
+///     let extracted = dest_dir.join(key);
+///     assert_eq!(fs::read(&extracted).unwrap(), fs::read(Path::new("./tests").join(key)).unwrap());
+///
+///     let modified = fs::metadata(&extracted).unwrap().modified().unwrap();
+///     assert_eq!(modified.duration_since(UNIX_EPOCH).unwrap().as_secs(), resource.modified);
+/// }
+/// # }
+/// ```
+///
+/// Permissions stored with [`ResourceDir::with_modes`](crate::ResourceDir::with_modes) are restored:
+///
+/// ```rust
+/// # #[cfg(all(feature = "extract", unix))]
+/// include!(concat!(env!("OUT_DIR"), "/generated_modes.rs"));
+///
+/// fn main() {
+/// #   #[cfg(all(feature = "extract", unix))]
+/// #   {
+///     use std::{env, fs, os::unix::fs::PermissionsExt, path::Path};
+///
+///     use static_files::resource::extract_all;
+///
+///     let dest_dir = Path::new(&env::var("OUT_DIR").unwrap()).join("extracted_modes");
+///     extract_all(&generate_modes(), &dest_dir).unwrap();
+///
+///     let mode = |path| fs::metadata(dest_dir.join(path)).unwrap().permissions().mode() & 0o7777;
+///     assert_eq!(mode("bin/init"), 0o755);
+///     assert_eq!(mode("etc/hostname"), 0o644);
+///
+///     // Up-to-date files get their permissions back too.
+///     fs::set_permissions(dest_dir.join("bin/init"), fs::Permissions::from_mode(0o600)).unwrap();
+///     extract_all(&generate_modes(), &dest_dir).unwrap();
+///     assert_eq!(mode("bin/init"), 0o755);
+/// #   }
+/// }
+/// ```
+///
+/// # Errors
+/// Returns an error if a file can't be written, or a key is not a relative path
+/// inside `dest_dir`, like `../file.txt`.
+#[cfg(feature = "extract")]
+pub fn extract_all<S: BuildHasher, P: AsRef<Path>>(
+    resources: &HashMap<&'static str, Resource, S>,
+    dest_dir: P,
+) -> io::Result<()> {
+    for (key, resource) in resources {
+        let relative = Path::new(key);
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("resource key {key:?} is not a relative path"),
+            ));
+        }
+
+        let path = dest_dir.as_ref().join(relative);
+        if !is_extracted(&path, resource)? {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, resource.data)?;
+        }
+        #[allow(clippy::cast_possible_wrap)]
+        let modified = filetime::FileTime::from_unix_time(resource.modified as i64, 0);
+        filetime::set_file_mtime(&path, modified)?;
+        #[cfg(feature = "xattr")]
+        resource.restore_xattrs(&path)?;
+        #[cfg(unix)]
+        if let Some(mode) = resource.mode {
+            use std::os::unix::fs::PermissionsExt;
+
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns whether the file `path` is an up-to-date extraction of `resource`.
+#[cfg(feature = "extract")]
+fn is_extracted(path: &Path, resource: &Resource) -> io::Result<bool> {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err),
+    };
+    if !metadata.is_file() || metadata.len() != resource.data.len() as u64 {
+        return Ok(false);
+    }
+
+    #[cfg(feature = "integrity")]
+    if let Some(etag) = resource.etag {
+        return Ok(sha256_hex(&fs::read(path)?) == etag);
+    }
+
+    let modified = filetime::FileTime::from_last_modification_time(&metadata);
+    Ok(u64::try_from(modified.unix_seconds()).ok() == Some(resource.modified))
+}
+
+/// Returns hex SHA-256 of resource `entries` sorted by key.
+#[cfg(feature = "integrity")]
+pub(crate) fn manifest_hash<'a, I>(entries: I) -> String
+where
+    I: IntoIterator<Item = (&'a str, &'a [u8])>,
+{
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    for (key, data) in entries {
+        hasher.update(key.as_bytes());
+        hasher.update([0]);
+        hasher.update((data.len() as u64).to_le_bytes());
+        hasher.update(data);
     }
+    hex(&hasher.finalize())
 }
 
+/// Returns hex SHA-256 of sorted hex SHA-256 digests of `contents`, ignoring their keys.
+#[cfg(feature = "integrity")]
+pub(crate) fn tree_hash<'a, I>(contents: I) -> String
+where
+    I: IntoIterator<Item = &'a [u8]>,
+{
+    use sha2::{Digest, Sha256};
+
+    let mut digests = contents.into_iter().map(sha256_hex).collect::<Vec<_>>();
+    digests.sort_unstable();
+    let mut hasher = Sha256::new();
+    for digest in digests {
+        hasher.update(digest.as_bytes());
+        hasher.update(b"\n");
+    }
+    hex(&hasher.finalize())
+}
+
+/// Entry of JSON manifest, collected while resources are generated.
+#[cfg(feature = "manifest")]
+pub(crate) struct ManifestEntry {
+    url: String,
+    size: usize,
+    mime: String,
+    /// Hex SHA-256 of the data.
+    hash: String,
+    modified: u64,
+}
+
+/// Manifest entries collected from set modules, possibly written in parallel.
+#[cfg(feature = "manifest")]
+pub(crate) type ManifestEntries = Arc<std::sync::Mutex<Vec<ManifestEntry>>>;
+
+/// Writes JSON manifest of collected `entries` into `manifest_path`, listing URL, size, mime type,
+/// hex SHA-256 and modification time of each resource sorted by URL.
+#[cfg(feature = "manifest")]
+pub(crate) fn write_json_manifest(
+    manifest_path: &Path,
+    entries: &ManifestEntries,
+) -> io::Result<()> {
+    let mut entries = entries
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    entries.sort_unstable_by(|entry, other| entry.url.cmp(&other.url));
+
+    let entries = entries
+        .iter()
+        .map(|entry| {
+            serde_json::json!({
+                "url": entry.url,
+                "size": entry.size,
+                "mime": entry.mime,
+                "hash": entry.hash,
+                "modified": entry.modified,
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut manifest = serde_json::to_vec_pretty(&serde_json::json!({ "resources": entries }))?;
+    manifest.push(b'\n');
+    fs::write(manifest_path, manifest)
+}
+
+/// Reads hex SHA-256 hashes of resources by key from JSON manifest at `manifest_path`,
+/// written by [`write_json_manifest`].
+#[cfg(feature = "manifest")]
+pub(crate) fn read_json_manifest_hashes(
+    manifest_path: &Path,
+) -> io::Result<HashMap<String, String>> {
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid JSON manifest {}", manifest_path.display()),
+        )
+    };
+
+    let manifest: serde_json::Value = serde_json::from_slice(&fs::read(manifest_path)?)?;
+    manifest["resources"]
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(
+            |entry| match (entry["url"].as_str(), entry["hash"].as_str()) {
+                (Some(url), Some(hash)) => Ok((
+                    url.strip_prefix('/').unwrap_or(url).to_owned(),
+                    hash.to_owned(),
+                )),
+                _ => Err(invalid()),
+            },
+        )
+        .collect()
+}
+
+/// Returns hex SHA-256 of `data`.
+#[cfg(feature = "integrity")]
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+
+    hex(&Sha256::digest(data))
+}
+
+/// Returns Subresource Integrity metadata of `data` with SHA-384 digest.
+#[cfg(feature = "integrity")]
+fn sri_sha384(data: &[u8]) -> String {
+    use sha2::{Digest, Sha384};
+
+    format!("sha384-{}", base64(&Sha384::digest(data)))
+}
+
+/// Returns standard padded base64 of `bytes`.
+#[cfg(feature = "integrity")]
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut base64 = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0_u32, |group, (index, &byte)| {
+                group | u32::from(byte) << (16 - 8 * index)
+            });
+        for index in 0..4 {
+            if index <= chunk.len() {
+                base64.push(char::from(
+                    ALPHABET[(group >> (18 - 6 * index) & 0x3f) as usize],
+                ));
+            } else {
+                base64.push('=');
+            }
+        }
+    }
+    base64
+}
+
+#[cfg(feature = "integrity")]
+fn hex(bytes: &[u8]) -> String {
+    use core::fmt::Write;
+
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+#[cfg(feature = "std")]
 pub(crate) const DEFAULT_VARIABLE_NAME: &str = "r";
 
+#[cfg(feature = "std")]
+pub(crate) const DEFAULT_RESOURCE_NAMESPACE: &str = "::static_files::resource";
+
+/// Hasher of the generated `HashMap`.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyHasher {
+    /// Default `std` hasher.
+    SipHash,
+    /// Fast deterministic hasher, keys are known at compile time and are not attacker-controlled.
+    ///
+    /// Generated map type is `HashMap<&'static str, Resource, FxBuildHasher>`.
+    ///
+    /// ```rust
+    /// use std::collections::HashMap;
+    ///
+    /// use static_files::{FxBuildHasher, Resource};
+    ///
+    /// include!(concat!(env!("OUT_DIR"), "/generated_fxhash.rs"));
+    ///
+    /// fn main() {
+    ///     let resources: HashMap<&'static str, Resource, FxBuildHasher> = generate_fxhash();
+    ///
+    ///     assert_eq!(resources.len(), 4);
+    ///     assert!(resources.contains_key("index.html"));
+    /// }
+    /// ```
+    #[cfg(feature = "fxhash")]
+    FxHash,
+}
+
+#[cfg(feature = "std")]
+impl Default for KeyHasher {
+    fn default() -> Self {
+        Self::SipHash
+    }
+}
+
+#[cfg(feature = "std")]
+impl KeyHasher {
+    pub(crate) fn type_parameter(self) -> &'static str {
+        match self {
+            Self::SipHash => "",
+            #[cfg(feature = "fxhash")]
+            Self::FxHash => ", ::static_files::FxBuildHasher",
+        }
+    }
+
+    pub(crate) fn build_hasher_type(self) -> &'static str {
+        match self {
+            Self::SipHash => "::std::collections::hash_map::RandomState",
+            #[cfg(feature = "fxhash")]
+            Self::FxHash => "::static_files::FxBuildHasher",
+        }
+    }
+
+    pub(crate) fn constructor(self) -> &'static str {
+        match self {
+            Self::SipHash => "new",
+            #[cfg(feature = "fxhash")]
+            Self::FxHash => "default",
+        }
+    }
+}
+
+/// Unicode normalization form of resource keys.
+#[cfg(feature = "unicode")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NormalizationForm {
+    /// Canonical composition, used by most systems, e.g. Linux and Windows.
+    Nfc,
+    /// Canonical decomposition, used by macOS file systems.
+    Nfd,
+    /// Compatibility composition.
+    Nfkc,
+    /// Compatibility decomposition.
+    Nfkd,
+}
+
+#[cfg(feature = "unicode")]
+impl NormalizationForm {
+    pub(crate) fn normalize(self, key: &str) -> String {
+        use unicode_normalization::UnicodeNormalization;
+
+        match self {
+            Self::Nfc => key.nfc().collect(),
+            Self::Nfd => key.nfd().collect(),
+            Self::Nfkc => key.nfkc().collect(),
+            Self::Nfkd => key.nfkd().collect(),
+        }
+    }
+}
+
+/// Resources of a generated function, use it to select one of several generated functions at runtime.
+///
+/// Implemented by types generated with [`ResourceDir::with_bundle_type`](crate::ResourceDir::with_bundle_type),
+/// `S` is the hasher of generated map.
+#[cfg(feature = "std")]
+pub trait ResourceBundle<S = RandomState> {
+    /// Returns resources of the generated function.
+    fn load(&self) -> HashMap<&'static str, Resource, S>;
+}
+
+/// Computes attributes of resources in a single pass over each file.
+///
+/// Use it to derive several attributes from shared state instead of setting
+/// a callback per attribute, see [`ResourceDir::with_resource_builder`](crate::ResourceDir::with_resource_builder).
+#[cfg(feature = "std")]
+pub trait ResourceBuilder: Send + Sync {
+    /// Returns attributes of the resource file described by `context`.
+    fn build(&self, context: &FileContext) -> ResourceAttrs;
+}
+
+/// Resource file passed to [`ResourceBuilder::build`].
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct FileContext<'a> {
+    pub path: &'a Path,
+    pub key: &'a str,
+    pub data: &'a [u8],
+    pub metadata: &'a Metadata,
+    /// Mime type detected from the file name or content.
+    pub mime_type: &'a str,
+}
+
+/// Decision about a collected resource file,
+/// see [`ResourceDir::with_entry_action`](crate::ResourceDir::with_entry_action).
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EntryAction {
+    /// The file is not embedded.
+    Skip,
+    /// The file is embedded under its key.
+    Keep,
+    /// The file is embedded under the given key.
+    Rename(String),
+}
+
+/// Resource attributes returned by [`ResourceBuilder::build`].
+///
+/// Set attributes override the ones computed by other options, unset ones keep them.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResourceAttrs {
+    /// Mime type stored in [`Resource::mime_type`], text detection still uses the detected one.
+    pub mime_type: Option<String>,
+    /// Stored in [`Resource::download_name`].
+    pub download_name: Option<String>,
+    /// Stored in [`Resource::vary`].
+    pub vary: Option<String>,
+    /// Sets [`Resource::immutable`].
+    pub immutable: bool,
+    /// `cfg` predicate of the resource, like in [`ResourceDir::with_cfg`](crate::ResourceDir::with_cfg).
+    pub cfg: Option<String>,
+}
+
 /// Generate resources for `project_dir` using `filter`.
 /// Result saved in `generated_filename` and function named as `fn_name`.
 ///
 /// in `build.rs`:
 /// ```rust
 /// use std::{env, path::Path};
-/// use static_files::resource::generate_resources;
+/// use static_files::resource::generate_resources;
+///
+/// fn main() {
+///     let out_dir = env::var("OUT_DIR").unwrap();
+///     let generated_filename = Path::new(&out_dir).join("generated.rs");
+///     generate_resources("./tests", None, generated_filename, "generate").unwrap();
+/// }
+/// ```
+///
+/// in `main.rs`:
+/// ```rust
+/// include!(concat!(env!("OUT_DIR"), "/generated.rs"));
+///
+/// fn main() {
+///     let generated_file = generate();
+///
+///     assert_eq!(generated_file.len(), 4);
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub fn generate_resources<P: AsRef<Path>, G: AsRef<Path>>(
+    project_dir: P,
+    filter: Option<fn(p: &Path) -> bool>,
+    generated_filename: G,
+    fn_name: &str,
+) -> io::Result<()> {
+    generate_resources_with_filter(
+        project_dir,
+        |path| filter.map_or(true, |filter| filter(path)),
+        generated_filename,
+        fn_name,
+    )
+}
+
+/// Same as [`generate_resources`], but `filter` can be a closure capturing its environment.
+///
+/// ```rust
+/// use std::{env, fs, path::Path};
+/// use static_files::resource::generate_resources_with_filter;
+///
+/// let skipped = Path::new("./tests/file1.txt").canonicalize().unwrap();
+/// let generated_filename = env::temp_dir().join("static_files_with_filter.rs");
+/// generate_resources_with_filter(
+///     "./tests",
+///     |path| path.canonicalize().map_or(true, |path| path != skipped),
+///     &generated_filename,
+///     "generate",
+/// )
+/// .unwrap();
+///
+/// let generated = fs::read_to_string(&generated_filename).unwrap();
+/// assert!(!generated.contains("file1.txt"));
+/// assert!(generated.contains("file2.txt"));
+/// ```
+#[cfg(feature = "std")]
+pub fn generate_resources_with_filter<P, F, G>(
+    project_dir: P,
+    filter: F,
+    generated_filename: G,
+    fn_name: &str,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    F: Fn(&Path) -> bool,
+    G: AsRef<Path>,
+{
+    let resources = collect_resources(&project_dir, Some(filter))?;
+
+    let mut f = File::create(&generated_filename)?;
+
+    generate_function_header(
+        &mut f,
+        fn_name,
+        KeyHasher::default(),
+        DEFAULT_RESOURCE_NAMESPACE,
+    )?;
+    generate_uses(&mut f, DEFAULT_RESOURCE_NAMESPACE)?;
+
+    generate_variable_header(&mut f, DEFAULT_VARIABLE_NAME, KeyHasher::default())?;
+    generate_resource_inserts(&mut f, &project_dir, DEFAULT_VARIABLE_NAME, &resources)?;
+    generate_variable_return(&mut f, DEFAULT_VARIABLE_NAME)?;
+
+    generate_function_end(&mut f)?;
+
+    Ok(())
+}
+
+/// Generate resource mapping for `project_dir` using `filter`.
+/// Result saved in `generated_filename` as anonymous block which returns `HashMap<&'static str, Resource>`.
+///
+/// in `build.rs`:
+/// ```rust
+///
+/// use std::{env, path::Path};
+/// use static_files::resource::generate_resources_mapping;
+///
+/// fn main() {
+///     let out_dir = env::var("OUT_DIR").unwrap();
+///     let generated_filename = Path::new(&out_dir).join("generated_mapping.rs");
+///     generate_resources_mapping("./tests", None, generated_filename).unwrap();
+/// }
+/// ```
+///
+/// in `main.rs`:
+/// ```rust
+/// use std::collections::HashMap;
+///
+/// use static_files::Resource;
+///
+/// fn generate_mapping() -> HashMap<&'static str, Resource> {
+///   include!(concat!(env!("OUT_DIR"), "/generated_mapping.rs"))
+/// }
+///
+/// fn main() {
+///     let generated_file = generate_mapping();
+///
+///     assert_eq!(generated_file.len(), 4);
+///
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub fn generate_resources_mapping<P: AsRef<Path>, G: AsRef<Path>>(
+    project_dir: P,
+    filter: Option<fn(p: &Path) -> bool>,
+    generated_filename: G,
+) -> io::Result<()> {
+    generate_resources_mapping_with_filter(
+        project_dir,
+        |path| filter.map_or(true, |filter| filter(path)),
+        generated_filename,
+    )
+}
+
+/// Same as [`generate_resources_mapping`], but `filter` can be a closure capturing its environment,
+/// see [`generate_resources_with_filter`].
+#[cfg(feature = "std")]
+pub fn generate_resources_mapping_with_filter<P, F, G>(
+    project_dir: P,
+    filter: F,
+    generated_filename: G,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    F: Fn(&Path) -> bool,
+    G: AsRef<Path>,
+{
+    let resources = collect_resources(&project_dir, Some(filter))?;
+
+    let mut f = File::create(&generated_filename)?;
+    writeln!(f, "{{")?;
+
+    generate_uses(&mut f, DEFAULT_RESOURCE_NAMESPACE)?;
+
+    generate_variable_header(&mut f, DEFAULT_VARIABLE_NAME, KeyHasher::default())?;
+
+    generate_resource_inserts(&mut f, &project_dir, DEFAULT_VARIABLE_NAME, &resources)?;
+
+    generate_variable_return(&mut f, DEFAULT_VARIABLE_NAME)?;
+
+    writeln!(f, "}}")?;
+    Ok(())
+}
+
+/// Generate resources for `project_dir` using `filter` as two functions sharing the same data.
+/// Result saved in `generated_filename`.
+///
+/// Function `map_fn_name` returns `HashMap<&'static str, Resource>` for lookups,
+/// function `list_fn_name` returns `Vec<(&'static str, Resource)>` sorted by key for listing.
+/// Every file is included only once, both functions reference the same data.
+///
+/// in `build.rs`:
+/// ```rust
+/// use std::{env, path::Path};
+/// use static_files::resource::generate_resources_map_and_list;
+///
+/// fn main() {
+///     let out_dir = env::var("OUT_DIR").unwrap();
+///     let generated_filename = Path::new(&out_dir).join("generated_map_and_list.rs");
+///     generate_resources_map_and_list("./tests", None, generated_filename, "generate_map", "generate_list").unwrap();
+/// }
+/// ```
+///
+/// in `main.rs`:
+/// ```rust
+/// include!(concat!(env!("OUT_DIR"), "/generated_map_and_list.rs"));
+///
+/// fn main() {
+///     let map = generate_map();
+///     let list = generate_list();
+///
+///     assert_eq!(map.len(), 4);
+///     assert_eq!(list.len(), 4);
+///     assert!(list.windows(2).all(|w| w[0].0 < w[1].0));
+///
+///     for (key, resource) in &list {
+///         assert!(std::ptr::eq(resource.data, map[key].data));
+///     }
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub fn generate_resources_map_and_list<P: AsRef<Path>, G: AsRef<Path>>(
+    project_dir: P,
+    filter: Option<fn(p: &Path) -> bool>,
+    generated_filename: G,
+    map_fn_name: &str,
+    list_fn_name: &str,
+) -> io::Result<()> {
+    generate_resources_map_and_list_with_filter(
+        project_dir,
+        |path| filter.map_or(true, |filter| filter(path)),
+        generated_filename,
+        map_fn_name,
+        list_fn_name,
+    )
+}
+
+/// Same as [`generate_resources_map_and_list`], but `filter` can be a closure capturing its environment,
+/// see [`generate_resources_with_filter`].
+#[cfg(feature = "std")]
+pub fn generate_resources_map_and_list_with_filter<P, F, G>(
+    project_dir: P,
+    filter: F,
+    generated_filename: G,
+    map_fn_name: &str,
+    list_fn_name: &str,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    F: Fn(&Path) -> bool,
+    G: AsRef<Path>,
+{
+    write_resources_map_and_list(
+        project_dir.as_ref(),
+        filter,
+        generated_filename.as_ref(),
+        (map_fn_name, list_fn_name),
+        false,
+    )
+}
+
+/// Same as [`generate_resources_map_and_list`], but includes files with paths relative
+/// to `generated_filename`, like [`ResourceDir::with_relative_includes`](crate::ResourceDir::with_relative_includes).
+///
+/// in `build.rs`:
+/// ```rust
+/// use std::{env, path::Path};
+/// use static_files::resource::generate_resources_map_and_list_relative;
+///
+/// fn main() {
+///     let out_dir = env::var("OUT_DIR").unwrap();
+///     let generated_filename = Path::new(&out_dir).join("generated_map_and_list_relative.rs");
+///     generate_resources_map_and_list_relative(
+///         "./tests",
+///         None,
+///         generated_filename,
+///         "generate_map_relative",
+///         "generate_list_relative",
+///     )
+///     .unwrap();
+/// }
+/// ```
+///
+/// in `main.rs`:
+/// ```rust
+/// include!(concat!(env!("OUT_DIR"), "/generated_map_and_list_relative.rs"));
+///
+/// fn main() {
+///     assert_eq!(generate_map_relative().len(), 4);
+///     assert_eq!(generate_list_relative().len(), 4);
+///
+///     let resources_dir = std::fs::canonicalize("./tests").unwrap();
+///     let generated = include_str!(concat!(env!("OUT_DIR"), "/generated_map_and_list_relative.rs"));
+///     assert!(!generated.contains(resources_dir.to_str().unwrap()));
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub fn generate_resources_map_and_list_relative<P: AsRef<Path>, G: AsRef<Path>>(
+    project_dir: P,
+    filter: Option<fn(p: &Path) -> bool>,
+    generated_filename: G,
+    map_fn_name: &str,
+    list_fn_name: &str,
+) -> io::Result<()> {
+    generate_resources_map_and_list_relative_with_filter(
+        project_dir,
+        |path| filter.map_or(true, |filter| filter(path)),
+        generated_filename,
+        map_fn_name,
+        list_fn_name,
+    )
+}
+
+/// Same as [`generate_resources_map_and_list_relative`], but `filter` can be a closure capturing its environment,
+/// see [`generate_resources_with_filter`].
+#[cfg(feature = "std")]
+pub fn generate_resources_map_and_list_relative_with_filter<P, F, G>(
+    project_dir: P,
+    filter: F,
+    generated_filename: G,
+    map_fn_name: &str,
+    list_fn_name: &str,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    F: Fn(&Path) -> bool,
+    G: AsRef<Path>,
+{
+    write_resources_map_and_list(
+        project_dir.as_ref(),
+        filter,
+        generated_filename.as_ref(),
+        (map_fn_name, list_fn_name),
+        true,
+    )
+}
+
+#[cfg(feature = "std")]
+fn write_resources_map_and_list<F: Fn(&Path) -> bool>(
+    project_dir: &Path,
+    filter: F,
+    generated_filename: &Path,
+    (map_fn_name, list_fn_name): (&str, &str),
+    relative_includes: bool,
+) -> io::Result<()> {
+    let mut resources = collect_resources(project_dir, Some(filter))?
+        .into_iter()
+        .map(|resource| (resource_key(&project_dir, &resource.0), resource))
+        .collect::<Vec<_>>();
+    resources.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let infos = resources
+        .iter()
+        .map(|(_, (path, _))| ResourceInfo::read(path, None, &InsertOptions::default()))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let data_module = format!("{map_fn_name}_data");
+
+    let mut f = File::create(generated_filename)?;
+    let include_base = generated_include_base(generated_filename, relative_includes)?;
+
+    generate_resource_data_module(
+        &mut f,
+        &data_module,
+        &resources,
+        &infos,
+        include_base.as_deref(),
+    )?;
+
+    generate_function_header(
+        &mut f,
+        map_fn_name,
+        KeyHasher::default(),
+        DEFAULT_RESOURCE_NAMESPACE,
+    )?;
+    generate_constructor_uses(&mut f, DEFAULT_RESOURCE_NAMESPACE)?;
+    generate_variable_header(&mut f, DEFAULT_VARIABLE_NAME, KeyHasher::default())?;
+    for (index, (key_path, resource)) in resources.iter().enumerate() {
+        let constructor = resource_constructor(
+            &format!("{data_module}::D{index}"),
+            &infos[index],
+            resource,
+            DEFAULT_RESOURCE_NAMESPACE,
+        );
+        writeln!(
+            f,
+            "{DEFAULT_VARIABLE_NAME}.insert({key_path:?},{constructor});"
+        )?;
+    }
+    generate_variable_return(&mut f, DEFAULT_VARIABLE_NAME)?;
+    generate_function_end(&mut f)?;
+
+    writeln!(
+        f,
+        "#[allow(clippy::unreadable_literal)] pub fn {list_fn_name}() -> ::std::vec::Vec<(&'static str, ::static_files::Resource)> {{",
+    )?;
+    generate_constructor_uses(&mut f, DEFAULT_RESOURCE_NAMESPACE)?;
+    writeln!(f, "::std::vec![")?;
+    for (index, (key_path, resource)) in resources.iter().enumerate() {
+        let constructor = resource_constructor(
+            &format!("{data_module}::D{index}"),
+            &infos[index],
+            resource,
+            DEFAULT_RESOURCE_NAMESPACE,
+        );
+        writeln!(f, "({key_path:?},{constructor}),")?;
+    }
+    writeln!(f, "]")?;
+    generate_function_end(&mut f)?;
+
+    Ok(())
+}
+
+/// Generate resources for `project_dir` using `filter` as a static slice.
+/// Result saved in `generated_filename` as `pub static` named `static_name`
+/// of type `&[(&str, Resource)]` sorted by key.
+///
+/// Generated code does not depend on `std`, use it in `no_std` crates
+/// with `static-files` dependency declared with `default-features = false`.
+///
+/// in `build.rs`:
+/// ```rust
+/// use std::{env, path::Path};
+/// use static_files::resource::generate_resources_slice;
 ///
 /// fn main() {
 ///     let out_dir = env::var("OUT_DIR").unwrap();
-///     let generated_filename = Path::new(&out_dir).join("generated.rs");
-///     generate_resources("./tests", None, generated_filename, "generate").unwrap();
+///     let generated_filename = Path::new(&out_dir).join("generated_slice.rs");
+///     generate_resources_slice("./tests", None, generated_filename, "RESOURCES").unwrap();
 /// }
 /// ```
 ///
 /// in `main.rs`:
 /// ```rust
-/// include!(concat!(env!("OUT_DIR"), "/generated.rs"));
+/// include!(concat!(env!("OUT_DIR"), "/generated_slice.rs"));
 ///
 /// fn main() {
-///     let generated_file = generate();
+///     assert_eq!(RESOURCES.len(), 4);
+///     assert!(RESOURCES.windows(2).all(|w| w[0].0 < w[1].0));
 ///
-///     assert_eq!(generated_file.len(), 4);
+///     let generated = include_str!(concat!(env!("OUT_DIR"), "/generated_slice.rs"));
+///     assert!(!generated.contains("::std"));
 /// }
 /// ```
-pub fn generate_resources<P: AsRef<Path>, G: AsRef<Path>>(
+#[cfg(feature = "std")]
+pub fn generate_resources_slice<P: AsRef<Path>, G: AsRef<Path>>(
     project_dir: P,
     filter: Option<fn(p: &Path) -> bool>,
     generated_filename: G,
-    fn_name: &str,
+    static_name: &str,
 ) -> io::Result<()> {
-    let resources = collect_resources(&project_dir, filter)?;
-
-    let mut f = File::create(&generated_filename)?;
-
-    generate_function_header(&mut f, fn_name)?;
-    generate_uses(&mut f)?;
-
-    generate_variable_header(&mut f, DEFAULT_VARIABLE_NAME)?;
-    generate_resource_inserts(&mut f, &project_dir, DEFAULT_VARIABLE_NAME, &resources)?;
-    generate_variable_return(&mut f, DEFAULT_VARIABLE_NAME)?;
-
-    generate_function_end(&mut f)?;
+    generate_resources_slice_with_filter(
+        project_dir,
+        |path| filter.map_or(true, |filter| filter(path)),
+        generated_filename,
+        static_name,
+    )
+}
 
-    Ok(())
+/// Same as [`generate_resources_slice`], but `filter` can be a closure capturing its environment,
+/// see [`generate_resources_with_filter`].
+#[cfg(feature = "std")]
+pub fn generate_resources_slice_with_filter<P, F, G>(
+    project_dir: P,
+    filter: F,
+    generated_filename: G,
+    static_name: &str,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    F: Fn(&Path) -> bool,
+    G: AsRef<Path>,
+{
+    write_resources_slice(
+        project_dir.as_ref(),
+        filter,
+        generated_filename.as_ref(),
+        static_name,
+        false,
+    )
 }
 
-/// Generate resource mapping for `project_dir` using `filter`.
-/// Result saved in `generated_filename` as anonymous block which returns `HashMap<&'static str, Resource>`.
+/// Same as [`generate_resources_slice`], but includes files with paths relative
+/// to `generated_filename`, like [`ResourceDir::with_relative_includes`](crate::ResourceDir::with_relative_includes).
 ///
 /// in `build.rs`:
 /// ```rust
-///
 /// use std::{env, path::Path};
-/// use static_files::resource::generate_resources_mapping;
+/// use static_files::resource::generate_resources_slice_relative;
 ///
 /// fn main() {
 ///     let out_dir = env::var("OUT_DIR").unwrap();
-///     let generated_filename = Path::new(&out_dir).join("generated_mapping.rs");
-///     generate_resources_mapping("./tests", None, generated_filename).unwrap();
+///     let generated_filename = Path::new(&out_dir).join("generated_slice_relative.rs");
+///     generate_resources_slice_relative("./tests", None, generated_filename, "RESOURCES").unwrap();
 /// }
 /// ```
 ///
 /// in `main.rs`:
 /// ```rust
-/// use std::collections::HashMap;
-///
-/// use static_files::Resource;
-///
-/// fn generate_mapping() -> HashMap<&'static str, Resource> {
-///   include!(concat!(env!("OUT_DIR"), "/generated_mapping.rs"))
-/// }
+/// include!(concat!(env!("OUT_DIR"), "/generated_slice_relative.rs"));
 ///
 /// fn main() {
-///     let generated_file = generate_mapping();
-///
-///     assert_eq!(generated_file.len(), 4);
+///     assert_eq!(RESOURCES.len(), 4);
 ///
+///     let resources_dir = std::fs::canonicalize("./tests").unwrap();
+///     let generated = include_str!(concat!(env!("OUT_DIR"), "/generated_slice_relative.rs"));
+///     assert!(!generated.contains(resources_dir.to_str().unwrap()));
 /// }
 /// ```
-pub fn generate_resources_mapping<P: AsRef<Path>, G: AsRef<Path>>(
+#[cfg(feature = "std")]
+pub fn generate_resources_slice_relative<P: AsRef<Path>, G: AsRef<Path>>(
     project_dir: P,
     filter: Option<fn(p: &Path) -> bool>,
     generated_filename: G,
+    static_name: &str,
 ) -> io::Result<()> {
-    let resources = collect_resources(&project_dir, filter)?;
-
-    let mut f = File::create(&generated_filename)?;
-    writeln!(f, "{{")?;
+    generate_resources_slice_relative_with_filter(
+        project_dir,
+        |path| filter.map_or(true, |filter| filter(path)),
+        generated_filename,
+        static_name,
+    )
+}
 
-    generate_uses(&mut f)?;
+/// Same as [`generate_resources_slice_relative`], but `filter` can be a closure capturing its environment,
+/// see [`generate_resources_with_filter`].
+#[cfg(feature = "std")]
+pub fn generate_resources_slice_relative_with_filter<P, F, G>(
+    project_dir: P,
+    filter: F,
+    generated_filename: G,
+    static_name: &str,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    F: Fn(&Path) -> bool,
+    G: AsRef<Path>,
+{
+    write_resources_slice(
+        project_dir.as_ref(),
+        filter,
+        generated_filename.as_ref(),
+        static_name,
+        true,
+    )
+}
 
-    generate_variable_header(&mut f, DEFAULT_VARIABLE_NAME)?;
+#[cfg(feature = "std")]
+#[allow(clippy::unnecessary_debug_formatting)]
+fn write_resources_slice<F: Fn(&Path) -> bool>(
+    project_dir: &Path,
+    filter: F,
+    generated_filename: &Path,
+    static_name: &str,
+    relative_includes: bool,
+) -> io::Result<()> {
+    let mut resources = collect_resources(project_dir, Some(filter))?
+        .into_iter()
+        .map(|resource| (resource_key(&project_dir, &resource.0), resource))
+        .collect::<Vec<_>>();
+    resources.sort_by(|(a, _), (b, _)| a.cmp(b));
 
-    generate_resource_inserts(&mut f, &project_dir, DEFAULT_VARIABLE_NAME, &resources)?;
+    let mut f = File::create(generated_filename)?;
+    let include_base = generated_include_base(generated_filename, relative_includes)?;
 
-    generate_variable_return(&mut f, DEFAULT_VARIABLE_NAME)?;
+    writeln!(
+        f,
+        "#[allow(clippy::unreadable_literal)] pub static {static_name}: &[(&str, ::static_files::Resource)] = {{",
+    )?;
+    generate_constructor_uses(&mut f, DEFAULT_RESOURCE_NAMESPACE)?;
+    writeln!(f, "&[")?;
+    for (key_path, resource) in &resources {
+        let include_path = include_path(&resource.0, include_base.as_deref())?;
+        let info = ResourceInfo::read(&resource.0, None, &InsertOptions::default())?;
+        let include = if info.text { "str" } else { "bytes" };
+        let constructor = resource_constructor(
+            &format!("::core::include_{include}!({include_path:?})"),
+            &info,
+            resource,
+            DEFAULT_RESOURCE_NAMESPACE,
+        );
+        writeln!(f, "({key_path:?},{constructor}),")?;
+    }
+    writeln!(f, "]")?;
+    writeln!(f, "}};")?;
 
-    writeln!(f, "}}")?;
     Ok(())
 }
 
-pub(crate) fn collect_resources<P: AsRef<Path>>(
+#[cfg(feature = "std")]
+#[allow(clippy::unnecessary_debug_formatting)]
+fn generate_resource_data_module<W: Write>(
+    f: &mut W,
+    data_module: &str,
+    resources: &[(String, (PathBuf, Metadata))],
+    infos: &[ResourceInfo],
+    include_base: Option<&Path>,
+) -> io::Result<()> {
+    writeln!(f, "mod {data_module} {{")?;
+    for (index, (_, (path, _))) in resources.iter().enumerate() {
+        let include_path = include_path(path, include_base)?;
+        if infos[index].text {
+            writeln!(
+                f,
+                "pub(super) static D{index}: &str = ::std::include_str!({include_path:?});"
+            )?;
+        } else {
+            writeln!(
+                f,
+                "pub(super) static D{index}: &[u8] = ::std::include_bytes!({include_path:?});"
+            )?;
+        }
+    }
+    writeln!(f, "}}")
+}
+
+/// Returns the directory of created `generated_filename` if includes are relative to it.
+#[cfg(feature = "std")]
+fn generated_include_base(
+    generated_filename: &Path,
+    relative_includes: bool,
+) -> io::Result<Option<PathBuf>> {
+    if !relative_includes {
+        return Ok(None);
+    }
+    Ok(generated_filename
+        .canonicalize()?
+        .parent()
+        .map(Path::to_path_buf))
+}
+
+#[cfg(feature = "std")]
+#[allow(clippy::needless_pass_by_value)]
+pub(crate) fn collect_resources<P, F>(
     path: P,
-    filter: Option<fn(p: &Path) -> bool>,
-) -> io::Result<Vec<(PathBuf, Metadata)>> {
+    filter: Option<F>,
+) -> io::Result<Vec<(PathBuf, Metadata)>>
+where
+    P: AsRef<Path>,
+    F: Fn(&Path) -> bool,
+{
     let mut result = vec![];
+    // Explicit stack of directories being traversed, so the depth of the tree
+    // is not limited by the call stack. Entries are read upfront to not keep
+    // a file descriptor open per level.
+    let mut dirs = vec![read_dir_entries(path.as_ref())?];
 
-    for entry in fs::read_dir(&path)? {
-        let entry = entry?;
+    while let Some(entries) = dirs.last_mut() {
+        let entry = if let Some(entry) = entries.next() {
+            entry
+        } else {
+            dirs.pop();
+            continue;
+        };
         let path = entry.path();
 
         if let Some(ref filter) = filter {
@@ -149,8 +1901,7 @@ pub(crate) fn collect_resources<P: AsRef<Path>>(
         }
 
         if path.is_dir() {
-            let nested = collect_resources(path, filter)?;
-            result.extend(nested);
+            dirs.push(read_dir_entries(&path)?);
         } else {
             result.push((path, entry.metadata()?));
         }
@@ -159,6 +1910,14 @@ pub(crate) fn collect_resources<P: AsRef<Path>>(
     Ok(result)
 }
 
+#[cfg(feature = "std")]
+fn read_dir_entries(path: &Path) -> io::Result<std::vec::IntoIter<fs::DirEntry>> {
+    Ok(fs::read_dir(path)?
+        .collect::<io::Result<Vec<_>>>()?
+        .into_iter())
+}
+
+#[cfg(feature = "std")]
 pub(crate) fn generate_resource_inserts<P: AsRef<Path>, W: Write>(
     f: &mut W,
     project_dir: &P,
@@ -166,63 +1925,821 @@ pub(crate) fn generate_resource_inserts<P: AsRef<Path>, W: Write>(
     resources: &[(PathBuf, Metadata)],
 ) -> io::Result<()> {
     for resource in resources {
-        generate_resource_insert(f, project_dir, variable_name, resource)?;
+        generate_resource_insert(
+            f,
+            project_dir,
+            variable_name,
+            resource,
+            None,
+            &InsertOptions::default(),
+            None,
+        )?;
     }
     Ok(())
 }
 
+#[cfg(feature = "std")]
+#[allow(clippy::unnecessary_debug_formatting)]
 pub(crate) fn generate_resource_insert<P: AsRef<Path>, W: Write>(
     f: &mut W,
     project_dir: &P,
     variable_name: &str,
     resource: &(PathBuf, Metadata),
+    include_base: Option<&Path>,
+    options: &InsertOptions,
+    order: Option<u32>,
 ) -> io::Result<()> {
     let (path, metadata) = resource;
-    let abs_path = path.canonicalize()?;
-    let key_path = path.strip_prefix(project_dir).unwrap().to_slash().unwrap();
+    let key_path = options.resource_key(project_dir, path);
 
-    let modified = if let Ok(Ok(modified)) = metadata
-        .modified()
-        .map(|x| x.duration_since(SystemTime::UNIX_EPOCH))
-    {
-        modified.as_secs()
+    let inline = metadata.len() < options.inline_threshold;
+    #[cfg(feature = "manifest")]
+    let read_content = inline || options.resource_builder.is_some() || options.manifest.is_some();
+    #[cfg(not(feature = "manifest"))]
+    let read_content = inline || options.resource_builder.is_some();
+    let content = if read_content {
+        Some(fs::read(path)?)
     } else {
-        0
+        None
     };
-    let mime_type = mime_guess::MimeGuess::from_path(path).first_or_octet_stream();
+
+    let mut info = ResourceInfo::read_for_key(path, content.as_deref(), &key_path, options)?;
+    info.order = order;
+
+    let mut cfg = options.cfg.and_then(|cfg| cfg(&key_path));
+    if let (Some(builder), Some(content)) = (&options.resource_builder, &content) {
+        let attrs = builder.build(&FileContext {
+            path,
+            key: &key_path,
+            data: content,
+            metadata,
+            mime_type: info.mime_type.as_ref(),
+        });
+        cfg = info.apply(attrs)?.or(cfg);
+    }
+
+    #[cfg(feature = "manifest")]
+    if let (Some(manifest), Some(content)) = (&options.manifest, &content) {
+        let entry = ManifestEntry {
+            url: format!("/{key_path}"),
+            size: content.len(),
+            mime: info.mime_type.to_string(),
+            hash: info.etag.clone().unwrap_or_else(|| sha256_hex(content)),
+            modified: info.resource_modified(metadata),
+        };
+        manifest
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .push(entry);
+    }
+
+    let data = match content {
+        Some(content) if inline => {
+            if info.text {
+                format!("{:?}", String::from_utf8_lossy(&content))
+            } else {
+                byte_string_literal(&content)
+            }
+        }
+        _ => {
+            let include_path = include_path(path, include_base)?;
+            let include = if info.text { "s" } else { "i" };
+            format!("{include}!({include_path:?})")
+        }
+    };
+    let constructor = resource_constructor(&data, &info, resource, &options.resource_namespace);
+
+    let cfg = cfg
+        .map(|predicate| format!("#[cfg({predicate})] "))
+        .unwrap_or_default();
+
     writeln!(
         f,
-        "{variable_name}.insert({key_path:?},n(i!({abs_path:?}),{modified:?},{mime_type:?}));",
-    )
+        "{cfg}{variable_name}.insert({key_path:?},{constructor});"
+    )?;
+
+    if options.query_aliases {
+        if let Some(base_key) = strip_query(&key_path) {
+            // Resource with the exact key takes precedence regardless of insertion order.
+            writeln!(
+                f,
+                "{cfg}{variable_name}.entry({base_key:?}).or_insert_with(||{constructor});"
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns byte string literal of `bytes`, like `b"\x00abc"`.
+#[cfg(feature = "std")]
+fn byte_string_literal(bytes: &[u8]) -> String {
+    let mut literal = String::from("b\"");
+    for &byte in bytes {
+        literal.extend(std::ascii::escape_default(byte).map(char::from));
+    }
+    literal.push('"');
+    literal
+}
+
+/// Returns `key` without `?`-suffix of its file name, like `app.js` for `app.js?v=123`.
+#[cfg(feature = "std")]
+fn strip_query(key: &str) -> Option<&str> {
+    let file_name_start = key.rfind('/').map_or(0, |index| index + 1);
+    let query_start = file_name_start + key[file_name_start..].find('?')?;
+
+    Some(&key[..query_start]).filter(|base_key| base_key.len() > file_name_start)
+}
+
+/// Returns the path of `path` for include macros,
+/// absolute or relative to `include_base` directory.
+#[cfg(feature = "std")]
+fn include_path(path: &Path, include_base: Option<&Path>) -> io::Result<PathBuf> {
+    let abs_path = path.canonicalize()?;
+
+    let include_base = match include_base {
+        Some(include_base) => include_base,
+        None => return Ok(abs_path),
+    };
+
+    relative_path(&abs_path, include_base)
+        .filter(|relative_path| {
+            include_base
+                .join(relative_path)
+                .canonicalize()
+                .map_or(false, |resolved| resolved == abs_path)
+        })
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "cannot include {} relative to {}",
+                    abs_path.display(),
+                    include_base.display()
+                ),
+            )
+        })
+}
+
+#[cfg(feature = "std")]
+fn relative_path(path: &Path, base: &Path) -> Option<PathBuf> {
+    let mut path_components = path.components().peekable();
+    let mut base_components = base.components().peekable();
+
+    while let (Some(a), Some(b)) = (path_components.peek(), base_components.peek()) {
+        if a != b {
+            break;
+        }
+        path_components.next();
+        base_components.next();
+    }
+
+    let mut relative_path = PathBuf::new();
+    for component in base_components {
+        match component {
+            Component::Normal(_) => relative_path.push(".."),
+            _ => return None,
+        }
+    }
+    for component in path_components {
+        match component {
+            Component::Normal(_) => relative_path.push(component),
+            _ => return None,
+        }
+    }
+
+    Some(relative_path)
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn resource_key<P: AsRef<Path>>(project_dir: &P, path: &Path) -> String {
+    path.strip_prefix(project_dir)
+        .unwrap()
+        .to_slash()
+        .unwrap()
+        .into_owned()
+}
+
+/// Options of generated resources.
+#[cfg(feature = "std")]
+#[derive(Clone)]
+#[allow(clippy::struct_excessive_bools)]
+pub(crate) struct InsertOptions {
+    pub(crate) preload_hints: bool,
+    pub(crate) download_names: bool,
+    pub(crate) vary_hints: bool,
+    pub(crate) query_aliases: bool,
+    pub(crate) order: bool,
+    /// Appends charset to mime types of text resources.
+    pub(crate) charset: bool,
+    /// Files smaller than this size are inlined as literals instead of included.
+    pub(crate) inline_threshold: u64,
+    /// Returns `cfg` predicate of the resource with the given key.
+    pub(crate) cfg: Option<fn(&str) -> Option<String>>,
+    /// Returns whether the resource with the given file name is immutable.
+    pub(crate) immutable: Option<fn(&str) -> bool>,
+    /// Names of captured extended attributes.
+    #[cfg(feature = "xattr")]
+    pub(crate) xattrs: Vec<String>,
+    /// Path of the module providing `Resource` and its constructors.
+    pub(crate) resource_namespace: String,
+    /// Normalization form of resource keys.
+    #[cfg(feature = "unicode")]
+    pub(crate) unicode_normalization: Option<NormalizationForm>,
+    /// Computes hex SHA-256 of resources as their `ETag`.
+    #[cfg(feature = "integrity")]
+    pub(crate) etags: bool,
+    /// Computes Subresource Integrity metadata of resources.
+    #[cfg(feature = "integrity")]
+    pub(crate) sri: bool,
+    /// Captures Unix permission bits of resources.
+    pub(crate) modes: bool,
+    /// Modification times by keys overriding the ones of files.
+    pub(crate) modified: HashMap<String, u64>,
+    /// Modification time of all resources, overriding other ones.
+    pub(crate) fixed_modified: Option<u64>,
+    /// Latest modification time of resources, from `SOURCE_DATE_EPOCH` environment variable.
+    pub(crate) source_date_epoch: Option<u64>,
+    /// Mime types by lowercase file extensions overriding guessed ones.
+    pub(crate) mime_overrides: HashMap<String, String>,
+    /// Computes attributes overriding the ones of other options.
+    pub(crate) resource_builder: Option<Arc<dyn ResourceBuilder>>,
+    /// Keys of resource files overriding the ones computed from paths.
+    pub(crate) renamed: HashMap<PathBuf, String>,
+    /// Collects entries of JSON manifest of generated resources.
+    #[cfg(feature = "manifest")]
+    pub(crate) manifest: Option<ManifestEntries>,
+}
+
+#[cfg(feature = "std")]
+impl InsertOptions {
+    /// Returns the key of the resource file `path`.
+    pub(crate) fn resource_key<P: AsRef<Path>>(&self, project_dir: &P, path: &Path) -> String {
+        if let Some(key) = self.renamed.get(path) {
+            return key.clone();
+        }
+        let key = resource_key(project_dir, path);
+        #[cfg(feature = "unicode")]
+        if let Some(form) = self.unicode_normalization {
+            return form.normalize(&key);
+        }
+        key
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for InsertOptions {
+    fn default() -> Self {
+        Self {
+            preload_hints: false,
+            download_names: false,
+            vary_hints: false,
+            query_aliases: false,
+            order: false,
+            charset: false,
+            inline_threshold: 0,
+            cfg: None,
+            immutable: None,
+            #[cfg(feature = "xattr")]
+            xattrs: vec![],
+            resource_namespace: DEFAULT_RESOURCE_NAMESPACE.into(),
+            #[cfg(feature = "unicode")]
+            unicode_normalization: None,
+            #[cfg(feature = "integrity")]
+            etags: false,
+            #[cfg(feature = "integrity")]
+            sri: false,
+            modes: false,
+            modified: HashMap::new(),
+            fixed_modified: None,
+            source_date_epoch: source_date_epoch(),
+            mime_overrides: HashMap::new(),
+            resource_builder: None,
+            renamed: HashMap::new(),
+            #[cfg(feature = "manifest")]
+            manifest: None,
+        }
+    }
+}
+
+/// Build time properties of a collected file.
+#[cfg(feature = "std")]
+pub(crate) struct ResourceInfo {
+    mime_type: Mime,
+    text: bool,
+    #[cfg(feature = "image")]
+    dimensions: Option<(u32, u32)>,
+    preload: Vec<(String, String)>,
+    xattrs: Vec<(String, Vec<u8>)>,
+    download_name: Option<String>,
+    vary: Option<String>,
+    order: Option<u32>,
+    immutable: bool,
+    etag: Option<String>,
+    integrity: Option<String>,
+    mode: Option<u32>,
+    /// Modification time overriding the one of the file.
+    modified: Option<u64>,
+    /// Latest modification time, later ones are clamped to it.
+    max_modified: Option<u64>,
+}
+
+#[cfg(feature = "std")]
+impl ResourceInfo {
+    /// Reads info of the resource with key `key_path`, including its modification time
+    /// from `options`.
+    pub(crate) fn read_for_key(
+        path: &Path,
+        data: Option<&[u8]>,
+        key_path: &str,
+        options: &InsertOptions,
+    ) -> io::Result<Self> {
+        let mut info = Self::read(path, data, options)?;
+        info.modified = options.modified.get(key_path).copied();
+        if options.fixed_modified.is_some() {
+            info.modified = options.fixed_modified;
+            info.max_modified = None;
+        }
+        Ok(info)
+    }
+
+    /// Returns modification time of the resource in seconds since Unix epoch.
+    fn resource_modified(&self, metadata: &Metadata) -> u64 {
+        let modified = if let Some(modified) = self.modified {
+            modified
+        } else if let Ok(Ok(modified)) = metadata
+            .modified()
+            .map(|x| x.duration_since(SystemTime::UNIX_EPOCH))
+        {
+            modified.as_secs()
+        } else {
+            0
+        };
+        self.max_modified.map_or(modified, |max| modified.min(max))
+    }
+
+    /// Reads info of the resource file `path`, with its `data` if it is already read.
+    pub(crate) fn read(
+        path: &Path,
+        data: Option<&[u8]>,
+        options: &InsertOptions,
+    ) -> io::Result<Self> {
+        let read_data = || match data {
+            Some(data) => Ok(Cow::Borrowed(data)),
+            None => fs::read(path).map(Cow::Owned),
+        };
+        let mime_type = resource_mime_type(path, &options.mime_overrides)?;
+        let text_mime_type = mime_type.type_() == mime::TEXT
+            || [mime::JSON, mime::JAVASCRIPT, mime::XML].contains(&mime_type.subtype())
+            || [Some(mime::JSON), Some(mime::XML)].contains(&mime_type.suffix());
+        let mut charset = None;
+        let content = if text_mime_type {
+            match String::from_utf8(read_data()?.into_owned()) {
+                Ok(content) => {
+                    charset = Some("utf-8");
+                    Some(content)
+                }
+                Err(err) => {
+                    charset = non_utf8_charset(err.as_bytes());
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let text = content.is_some();
+
+        let preload = match content {
+            Some(html)
+                if options.preload_hints
+                    && mime_type.essence_str() == mime::TEXT_HTML.essence_str() =>
+            {
+                preload_hints(&html)
+            }
+            _ => vec![],
+        };
+
+        #[cfg(feature = "image")]
+        let dimensions = if mime_type.type_() == mime::IMAGE {
+            image_dimensions(path)
+        } else {
+            None
+        };
+
+        #[cfg(feature = "xattr")]
+        let xattrs = read_xattrs(path, &options.xattrs);
+        #[cfg(not(feature = "xattr"))]
+        let xattrs = vec![];
+
+        let download_name = if options.download_names {
+            path.file_name()
+                .map(|file_name| file_name.to_string_lossy().into_owned())
+        } else {
+            None
+        };
+
+        let vary = if options.vary_hints {
+            vary_hint(path, &mime_type)?.map(String::from)
+        } else {
+            None
+        };
+
+        let immutable = options.immutable.map_or(false, |immutable| {
+            path.file_name()
+                .map_or(false, |file_name| immutable(&file_name.to_string_lossy()))
+        });
+
+        #[cfg(feature = "integrity")]
+        let (etag, integrity) = if options.etags || options.sri {
+            let data = read_data()?;
+            (
+                options.etags.then(|| sha256_hex(&data)),
+                options.sri.then(|| sri_sha384(&data)),
+            )
+        } else {
+            (None, None)
+        };
+        #[cfg(not(feature = "integrity"))]
+        let (etag, integrity) = (None, None);
+
+        let mode = read_mode(path, options)?;
+
+        // Added last, as other properties are computed for the essence of mime type.
+        // Overridden mime types keep their own charset.
+        let mime_type = match charset {
+            Some(charset) if options.charset && mime_type.get_param(mime::CHARSET).is_none() => {
+                format!("{mime_type}; charset={charset}")
+                    .parse()
+                    .unwrap_or(mime_type)
+            }
+            _ => mime_type,
+        };
+
+        Ok(Self {
+            mime_type,
+            text,
+            #[cfg(feature = "image")]
+            dimensions,
+            preload,
+            xattrs,
+            download_name,
+            vary,
+            order: None,
+            immutable,
+            etag,
+            integrity,
+            mode,
+            modified: None,
+            max_modified: options.source_date_epoch,
+        })
+    }
+
+    /// Overrides properties set in `attrs` of a resource builder, returns its `cfg` predicate.
+    fn apply(&mut self, attrs: ResourceAttrs) -> io::Result<Option<String>> {
+        if let Some(mime_type) = attrs.mime_type {
+            self.mime_type = mime_type.parse().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid mime type {mime_type:?}"),
+                )
+            })?;
+        }
+        if attrs.download_name.is_some() {
+            self.download_name = attrs.download_name;
+        }
+        if attrs.vary.is_some() {
+            self.vary = attrs.vary;
+        }
+        self.immutable |= attrs.immutable;
+
+        Ok(attrs.cfg)
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn resource_mime_type(
+    path: &Path,
+    overrides: &HashMap<String, String>,
+) -> io::Result<Mime> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    if let Some(mime_type) = extension.and_then(|extension| overrides.get(&extension)) {
+        return mime_type.parse().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid mime type {mime_type:?}"),
+            )
+        });
+    }
+
+    let mime_type = mime_guess::MimeGuess::from_path(path).first_or_octet_stream();
+
+    #[cfg(feature = "mime-sniffing")]
+    if mime_type == mime::APPLICATION_OCTET_STREAM {
+        if let Some(mime_type) = sniff_mime_type(path)? {
+            return Ok(mime_type);
+        }
+    }
+
+    Ok(mime_type)
+}
+
+/// Detects mime type by content, `None` if content is not recognized.
+///
+/// Files with known magic bytes get their mime type, other non-empty UTF-8
+/// files without control characters are `text/plain`.
+///
+/// ```rust
+/// use std::{env, fs, path::Path};
+/// use static_files::resource::generate_resources;
+///
+/// let sniffing_dir = Path::new(&env::var("OUT_DIR").unwrap()).join("sniffing");
+/// fs::create_dir_all(&sniffing_dir).unwrap();
+/// fs::write(sniffing_dir.join("LICENSE"), "Permission is hereby granted\n").unwrap();
+/// fs::write(sniffing_dir.join("pixel"), b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR").unwrap();
+/// fs::write(sniffing_dir.join("blob"), [0u8, 1, 2, 3]).unwrap();
+/// fs::write(sniffing_dir.join("pixel.txt"), b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR").unwrap();
+///
+/// let generated_filename = sniffing_dir.with_extension("rs");
+/// generate_resources(&sniffing_dir, None, &generated_filename, "generate").unwrap();
+///
+/// let generated = fs::read_to_string(generated_filename).unwrap();
+/// let mime_type = |key: &str| {
+///     let line = generated.lines().find(|line| line.contains(&format!("({key:?},"))).unwrap();
+///     line.rsplit(',').next().unwrap().trim_end_matches("));").trim_matches('"').to_owned()
+/// };
+/// assert_eq!(mime_type("LICENSE"), "text/plain");
+/// assert_eq!(mime_type("pixel"), "image/png");
+/// assert_eq!(mime_type("blob"), "application/octet-stream");
+/// assert_eq!(mime_type("pixel.txt"), "text/plain");
+/// ```
+#[cfg(feature = "mime-sniffing")]
+fn sniff_mime_type(path: &Path) -> io::Result<Option<Mime>> {
+    let data = fs::read(path)?;
+
+    if let Some(kind) = infer::get(&data) {
+        return Ok(kind.mime_type().parse().ok());
+    }
+
+    let text = std::str::from_utf8(&data).map_or(false, |text| {
+        !text.is_empty() && !text.chars().any(|c| c.is_control() && !c.is_whitespace())
+    });
+
+    Ok(if text { Some(mime::TEXT_PLAIN) } else { None })
+}
+
+/// Returns `SOURCE_DATE_EPOCH` environment variable of reproducible builds.
+#[cfg(feature = "std")]
+pub(crate) fn source_date_epoch() -> Option<u64> {
+    std::env::var("SOURCE_DATE_EPOCH").ok()?.trim().parse().ok()
+}
+
+#[cfg(feature = "std")]
+fn resource_constructor(
+    data: &str,
+    info: &ResourceInfo,
+    resource: &(PathBuf, Metadata),
+    resource_namespace: &str,
+) -> String {
+    let (_, metadata) = resource;
+
+    let modified = info.resource_modified(metadata);
+    let mime_type = &info.mime_type;
+
+    let mut constructor = format!(
+        "{}({data},{modified:?},{mime_type:?})",
+        if info.text { "t" } else { "n" }
+    );
+
+    #[cfg(feature = "image")]
+    if let Some((width, height)) = info.dimensions {
+        constructor = format!("{constructor}.with_dimensions({width},{height})");
+    }
+
+    if !info.preload.is_empty() {
+        let preload = info
+            .preload
+            .iter()
+            .map(|(href, destination)| {
+                format!("{resource_namespace}::PreloadHint{{href:{href:?},destination:{destination:?}}}")
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        constructor = format!("{constructor}.with_preload(&[{preload}])");
+    }
+
+    if !info.xattrs.is_empty() {
+        let xattrs = info
+            .xattrs
+            .iter()
+            .map(|(name, value)| {
+                format!("{resource_namespace}::Xattr{{name:{name:?},value:&{value:?}}}")
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        constructor = format!("{constructor}.with_xattrs(&[{xattrs}])");
+    }
+
+    if let Some(download_name) = &info.download_name {
+        constructor = format!("{constructor}.with_download_name({download_name:?})");
+    }
+
+    if let Some(vary) = &info.vary {
+        constructor = format!("{constructor}.with_vary({vary:?})");
+    }
+
+    if let Some(order) = info.order {
+        constructor = format!("{constructor}.with_order({order})");
+    }
+
+    if info.immutable {
+        constructor = format!("{constructor}.with_immutable()");
+    }
+
+    if let Some(etag) = &info.etag {
+        constructor = format!("{constructor}.with_etag({etag:?})");
+    }
+
+    if let Some(integrity) = &info.integrity {
+        constructor = format!("{constructor}.with_integrity({integrity:?})");
+    }
+
+    if let Some(mode) = info.mode {
+        constructor = format!("{constructor}.with_mode(0o{mode:o})");
+    }
+
+    constructor
+}
+
+/// Returns Unix permission bits of the file `path` if `options` capture them.
+#[cfg(feature = "std")]
+fn read_mode(path: &Path, options: &InsertOptions) -> io::Result<Option<u32>> {
+    #[cfg(unix)]
+    if options.modes {
+        use std::os::unix::fs::PermissionsExt;
+
+        return Ok(Some(fs::metadata(path)?.permissions().mode() & 0o7777));
+    }
+    #[cfg(not(unix))]
+    let _ = (path, options);
+
+    Ok(None)
+}
+
+/// Extensions of precompressed variants of a file.
+#[cfg(feature = "std")]
+const ENCODING_EXTENSIONS: [&str; 3] = ["br", "gz", "zst"];
+
+/// Returns `Vary` header value for the file, based on variants next to it.
+///
+/// Files with precompressed variants, like `app.js.gz`, vary by `Accept-Encoding`.
+/// Images with variants of the same name in other image formats, like `photo.avif`
+/// for `photo.jpg`, vary by `Accept`.
+#[cfg(feature = "std")]
+fn vary_hint(path: &Path, mime_type: &Mime) -> io::Result<Option<&'static str>> {
+    let encodings = ENCODING_EXTENSIONS.iter().any(|extension| {
+        let mut variant = path.as_os_str().to_owned();
+        variant.push(".");
+        variant.push(extension);
+        Path::new(&variant).is_file()
+    });
+
+    let mut formats = false;
+    if let (mime::IMAGE, Some(parent), Some(stem)) =
+        (mime_type.type_(), path.parent(), path.file_stem())
+    {
+        for entry in fs::read_dir(parent)? {
+            let variant = entry?.path();
+            formats |= variant != path
+                && variant.file_stem() == Some(stem)
+                && mime_guess::from_path(&variant)
+                    .first()
+                    .map_or(false, |mime_type| mime_type.type_() == mime::IMAGE);
+        }
+    }
+
+    Ok(match (encodings, formats) {
+        (true, true) => Some("Accept, Accept-Encoding"),
+        (true, false) => Some("Accept-Encoding"),
+        (false, true) => Some("Accept"),
+        (false, false) => None,
+    })
+}
+
+/// Detects charset of text content which is not valid UTF-8.
+#[cfg(feature = "charset")]
+#[allow(clippy::unnecessary_wraps)]
+fn non_utf8_charset(content: &[u8]) -> Option<&'static str> {
+    let mut detector = chardetng::EncodingDetector::new(chardetng::Iso2022JpDetection::Deny);
+    detector.feed(content, true);
+    Some(detector.guess(None, chardetng::Utf8Detection::Deny).name())
+}
+
+#[cfg(all(feature = "std", not(feature = "charset")))]
+fn non_utf8_charset(_content: &[u8]) -> Option<&'static str> {
+    None
+}
+
+/// Reads extended attributes `names` of the file, missing and unreadable attributes are skipped.
+#[cfg(all(feature = "xattr", unix))]
+fn read_xattrs(path: &Path, names: &[String]) -> Vec<(String, Vec<u8>)> {
+    names
+        .iter()
+        .filter_map(|name| {
+            let value = xattr::get(path, name).ok()??;
+            Some((name.clone(), value))
+        })
+        .collect()
+}
+
+#[cfg(all(feature = "xattr", not(unix)))]
+fn read_xattrs(_path: &Path, _names: &[String]) -> Vec<(String, Vec<u8>)> {
+    vec![]
+}
+
+/// Reads image dimensions from the file header, `None` if the format is not supported.
+///
+/// ```rust
+/// use std::{env, fs, path::Path};
+/// use static_files::resource::generate_resources;
+///
+/// let images_dir = Path::new(&env::var("OUT_DIR").unwrap()).join("images");
+/// fs::create_dir_all(&images_dir).unwrap();
+/// image::RgbImage::new(3, 2).save(images_dir.join("pixel.png")).unwrap();
+/// fs::write(images_dir.join("note.txt"), "not an image").unwrap();
+///
+/// let generated_filename = images_dir.with_extension("rs");
+/// generate_resources(&images_dir, None, &generated_filename, "generate").unwrap();
+///
+/// let generated = fs::read_to_string(generated_filename).unwrap();
+/// assert!(generated.contains("\"image/png\").with_dimensions(3,2));"));
+/// assert!(generated.contains("\"text/plain\"));"));
+/// ```
+#[cfg(feature = "image")]
+fn image_dimensions(path: &Path) -> Option<(u32, u32)> {
+    image::ImageReader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
 }
 
-pub(crate) fn generate_function_header<F: Write>(f: &mut F, fn_name: &str) -> io::Result<()> {
+#[cfg(feature = "std")]
+pub(crate) fn generate_function_header<F: Write>(
+    f: &mut F,
+    fn_name: &str,
+    key_hasher: KeyHasher,
+    resource_namespace: &str,
+) -> io::Result<()> {
     writeln!(
         f,
-        "#[allow(clippy::unreadable_literal)] pub fn {fn_name}() -> ::std::collections::HashMap<&'static str, ::static_files::Resource> {{",
+        "#[allow(clippy::unreadable_literal)] pub fn {fn_name}() -> ::std::collections::HashMap<&'static str, {resource_namespace}::Resource{}> {{",
+        key_hasher.type_parameter(),
     )
 }
 
+#[cfg(feature = "std")]
 pub(crate) fn generate_function_end<F: Write>(f: &mut F) -> io::Result<()> {
     writeln!(f, "}}")
 }
 
-pub(crate) fn generate_uses<F: Write>(f: &mut F) -> io::Result<()> {
+#[cfg(feature = "std")]
+pub(crate) fn generate_uses<F: Write>(f: &mut F, resource_namespace: &str) -> io::Result<()> {
+    generate_constructor_uses(f, resource_namespace)?;
     writeln!(
         f,
         "\
-use ::static_files::resource::new_resource as n;
-use ::std::include_bytes as i;",
+#[allow(unused_imports)] use ::std::include_bytes as i;
+#[allow(unused_imports)] use ::std::include_str as s;",
     )
 }
 
-pub(crate) fn generate_variable_header<F: Write>(f: &mut F, variable_name: &str) -> io::Result<()> {
+#[cfg(feature = "std")]
+fn generate_constructor_uses<F: Write>(f: &mut F, resource_namespace: &str) -> io::Result<()> {
+    writeln!(
+        f,
+        "\
+#[allow(unused_imports)] use {resource_namespace}::new_resource as n;
+#[allow(unused_imports)] use {resource_namespace}::new_text_resource as t;",
+    )
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn generate_variable_header<F: Write>(
+    f: &mut F,
+    variable_name: &str,
+    key_hasher: KeyHasher,
+) -> io::Result<()> {
     writeln!(
         f,
-        "let mut {variable_name} = ::std::collections::HashMap::new();",
+        "let mut {variable_name} = ::std::collections::HashMap::{}();",
+        key_hasher.constructor(),
     )
 }
 
+#[cfg(feature = "std")]
 pub(crate) fn generate_variable_return<F: Write>(f: &mut F, variable_name: &str) -> io::Result<()> {
     writeln!(f, "{variable_name}")
 }
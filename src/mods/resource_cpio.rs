@@ -0,0 +1,233 @@
+/*!
+Resources from a CPIO archive.
+*/
+use std::{
+    collections::HashMap,
+    env, fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use filetime::FileTime;
+
+use super::resource_dir::{resource_dir, ResourceDir};
+use super::sets::fnv1a_hash;
+
+/// Magic numbers of `newc` headers, without and with checksums.
+const NEWC_MAGICS: [&[u8]; 2] = [b"070701", b"070702"];
+const HEADER_LEN: usize = 110;
+const TRAILER: &[u8] = b"TRAILER!!!";
+
+/// Generate resources for regular files of the CPIO archive `archive_path` in `newc` format,
+/// like initramfs images.
+///
+/// Files are extracted to `OUT_DIR/resource_cpio/<archive file name>-<hash of archive path>`
+/// with modification time and, on Unix, mode bits of their archive entries. Mode bits are stored
+/// in [`Resource::mode`](crate::Resource::mode), see [`ResourceDir::with_modes`].
+/// Resource keys are archive paths, directories, symbolic links and device nodes are not embedded.
+/// Every path of a hard link group gets the data, which `newc` stores only in its last entry.
+///
+/// ```rust
+/// # #[cfg(unix)]
+/// # {
+/// use std::{env, fs, os::unix::fs::PermissionsExt, path::Path};
+/// use static_files::resource_cpio;
+///
+/// let entry = |name: &str, mode: u32, (ino, nlink): (u32, u32), data: &[u8]| {
+///     let mut entry = format!(
+///         "070701{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}",
+///         ino, mode, 0, 0, nlink, 1_700_000_000, data.len(), 0, 0, 0, 0, name.len() + 1, 0,
+///     )
+///     .into_bytes();
+///     entry.extend(name.as_bytes());
+///     entry.push(0);
+///     entry.resize((entry.len() + 3) / 4 * 4, 0);
+///     entry.extend(data);
+///     entry.resize((entry.len() + 3) / 4 * 4, 0);
+///     entry
+/// };
+///
+/// let out_dir = env::var("OUT_DIR").unwrap();
+/// let out_dir = Path::new(&out_dir);
+/// let archive_path = out_dir.join("rootfs.cpio");
+/// let archive = [
+///     entry("etc", 0o040_755, (1, 2), b""),
+///     entry("etc/hostname", 0o100_644, (2, 1), b"device\n"),
+///     entry("./bin/init", 0o100_755, (3, 1), b"#!/bin/sh\n"),
+///     entry("bin/sh", 0o120_777, (4, 1), b"init"),
+///     // Hard links, only the last entry carries the data.
+///     entry("bin/busybox", 0o100_755, (5, 2), b""),
+///     entry("bin/ls", 0o100_755, (5, 2), b"busybox"),
+///     entry("TRAILER!!!", 0, (0, 1), b""),
+/// ]
+/// .concat();
+/// fs::write(&archive_path, archive).unwrap();
+///
+/// let mut resources = resource_cpio(&archive_path).unwrap();
+/// resources
+///     .with_generated_filename(out_dir.join("generated_cpio.rs"))
+///     .with_generated_fn("cpio");
+/// resources.build().unwrap();
+///
+/// let set = fs::read_to_string(out_dir.join("cpio_sets/set_1.rs")).unwrap();
+/// assert!(set.contains("\"etc/hostname\""));
+/// assert!(set.contains("\"bin/init\""));
+/// assert!(!set.contains("\"bin/sh\""));
+///
+/// assert!(set.contains(".with_mode(0o644)"));
+/// assert!(set.contains(".with_mode(0o755)"));
+///
+/// let snapshot_dir = fs::read_dir(out_dir.join("resource_cpio"))
+///     .unwrap()
+///     .map(|entry| entry.unwrap().path())
+///     .find(|path| path.file_name().unwrap().to_str().unwrap().starts_with("rootfs.cpio-"))
+///     .unwrap();
+/// let mode = |path| fs::metadata(snapshot_dir.join(path)).unwrap().permissions().mode() & 0o7777;
+/// assert_eq!(mode("etc/hostname"), 0o644);
+/// assert_eq!(mode("bin/init"), 0o755);
+///
+/// let read = |path| fs::read_to_string(snapshot_dir.join(path)).unwrap();
+/// assert_eq!(read("bin/busybox"), "busybox");
+/// assert_eq!(read("bin/ls"), "busybox");
+///
+/// assert!(resource_cpio(out_dir.join("..")).is_err());
+/// # }
+/// ```
+///
+/// # Errors
+/// Returns [`io::ErrorKind::InvalidData`] error if the archive is not in `newc` format
+/// or has paths outside of the archive root, and [`io::ErrorKind::InvalidInput`] error
+/// if `archive_path` has no file name.
+///
+/// # Panics
+/// Panics if `OUT_DIR` environment variable is not set.
+pub fn resource_cpio<P: AsRef<Path>>(archive_path: P) -> io::Result<ResourceDir> {
+    let archive_path = archive_path.as_ref();
+    let file_name = archive_path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("archive path {} has no file name", archive_path.display()),
+        )
+    })?;
+    let archive = fs::read(archive_path)?;
+
+    let path_hash = fnv1a_hash(archive_path.canonicalize()?.to_string_lossy().bytes());
+    let snapshot_dir = Path::new(&env::var("OUT_DIR").unwrap())
+        .join("resource_cpio")
+        .join(format!("{}-{path_hash:016x}", file_name.to_string_lossy()));
+    if snapshot_dir.exists() {
+        fs::remove_dir_all(&snapshot_dir)?;
+    }
+    fs::create_dir_all(&snapshot_dir)?;
+
+    // Paths of hard link groups by device and inode, written with the data of the last entry.
+    let mut links: HashMap<_, (Vec<PathBuf>, u32, u32)> = HashMap::new();
+    let mut offset = 0;
+    loop {
+        let header = archive
+            .get(offset..offset + HEADER_LEN)
+            .filter(|header| NEWC_MAGICS.contains(&&header[..6]))
+            .ok_or_else(|| invalid_data("missing newc header"))?;
+        let inode = header_field(header, 0)?;
+        let mode = header_field(header, 1)?;
+        let nlink = header_field(header, 4)?;
+        let modified = header_field(header, 5)?;
+        let device = (header_field(header, 7)?, header_field(header, 8)?);
+        let file_size = header_field(header, 6)? as usize;
+        let name_size = header_field(header, 11)? as usize;
+
+        let name_start = offset + HEADER_LEN;
+        let data_start = align(name_start + name_size);
+        let data_end = data_start + file_size;
+        let name = archive
+            .get(name_start..(name_start + name_size).saturating_sub(1))
+            .ok_or_else(|| invalid_data("truncated entry name"))?;
+        if name == TRAILER {
+            for (paths, mode, modified) in links.into_values() {
+                write_entry(&paths, b"", mode, modified)?;
+            }
+            break;
+        }
+        let data = archive
+            .get(data_start..data_end)
+            .ok_or_else(|| invalid_data("truncated entry data"))?;
+
+        if mode & 0o170_000 == 0o100_000 {
+            let path = snapshot_dir.join(entry_path(name)?);
+            if nlink > 1 {
+                let link = links.entry((device, inode)).or_default();
+                link.0.push(path);
+                if data.is_empty() {
+                    link.1 = mode;
+                    link.2 = modified;
+                } else {
+                    let (paths, _, _) = links.remove(&(device, inode)).unwrap_or_default();
+                    write_entry(&paths, data, mode, modified)?;
+                }
+            } else {
+                write_entry(&[path], data, mode, modified)?;
+            }
+        }
+
+        offset = align(data_end);
+    }
+
+    let mut resource_dir = resource_dir(snapshot_dir);
+    resource_dir.with_modes(true);
+    Ok(resource_dir)
+}
+
+/// Writes `data` of an archive entry to `paths` with `mode` and `modified` time.
+#[cfg_attr(not(unix), allow(unused_variables))]
+fn write_entry(paths: &[PathBuf], data: &[u8], mode: u32, modified: u32) -> io::Result<()> {
+    for path in paths {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, data)?;
+        filetime::set_file_mtime(path, FileTime::from_unix_time(modified.into(), 0))?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o7777))?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns hexadecimal field `index` of `newc` `header`.
+fn header_field(header: &[u8], index: usize) -> io::Result<u32> {
+    let start = 6 + 8 * index;
+    std::str::from_utf8(&header[start..start + 8])
+        .ok()
+        .and_then(|field| u32::from_str_radix(field, 16).ok())
+        .ok_or_else(|| invalid_data("invalid newc header field"))
+}
+
+/// Returns relative path of archive entry `name`, without `.` components.
+fn entry_path(name: &[u8]) -> io::Result<PathBuf> {
+    let name = std::str::from_utf8(name).map_err(|_| invalid_data("entry name is not UTF-8"))?;
+    let mut path = PathBuf::new();
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(component) => path.push(component),
+            Component::CurDir => {}
+            _ => {
+                return Err(invalid_data(&format!(
+                    "entry {name} is outside of archive root"
+                )))
+            }
+        }
+    }
+    Ok(path)
+}
+
+/// Returns `offset` rounded up to the 4-byte alignment of `newc` entries.
+fn align(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -1,9 +1,21 @@
 use std::{
-    env, io,
+    collections::HashMap,
+    env, fs, io,
     path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::Arc,
 };
 
-use super::sets::{generate_resources_sets, SplitByCount};
+#[cfg(feature = "unicode")]
+use super::resource::NormalizationForm;
+use super::resource::{
+    source_date_epoch, EntryAction, FileContext, InsertOptions, KeyHasher, ResourceBuilder,
+    DEFAULT_RESOURCE_NAMESPACE,
+};
+use super::sets::{
+    generate_resources_sets_with_options, module_dir, EntryActionFn, KeyConflict, SetModuleNaming,
+    SetSplitStrategie, SetsOptions, SizeWarning, SplitByCount, SplitBySize, SplitByTopDirHash,
+};
 
 /// Generate resources for `resource_dir`.
 ///
@@ -15,6 +27,31 @@ use super::sets::{generate_resources_sets, SplitByCount};
 ///
 /// resource_dir("./tests").build().unwrap();
 /// ```
+///
+/// Nested directories are traversed without recursion, so deep trees
+/// like vendored `node_modules` are supported.
+///
+/// ```rust
+/// use std::{env, fs, path::Path};
+/// use static_files::resource_dir;
+///
+/// let out_dir = env::var("OUT_DIR").unwrap();
+/// let deep_dir = Path::new(&out_dir).join("deep");
+/// let _ = fs::remove_dir_all(&deep_dir);
+///
+/// let nested_dir = (0..1000).fold(deep_dir.clone(), |dir, _| dir.join("d"));
+/// fs::create_dir_all(&nested_dir).unwrap();
+/// fs::write(nested_dir.join("file.txt"), "deep").unwrap();
+///
+/// let mut resources = resource_dir(&deep_dir);
+/// resources
+///     .with_generated_filename(Path::new(&out_dir).join("generated_deep.rs"))
+///     .with_generated_fn("deep");
+/// resources.build().unwrap();
+///
+/// let set = fs::read_to_string(Path::new(&out_dir).join("deep_sets/set_1.rs")).unwrap();
+/// assert!(set.contains(&format!("\"{}file.txt\"", "d/".repeat(1000))));
+/// ```
 pub fn resource_dir<P: AsRef<Path>>(resource_dir: P) -> ResourceDir {
     ResourceDir {
         resource_dir: resource_dir.as_ref().into(),
@@ -26,53 +63,581 @@ pub fn resource_dir<P: AsRef<Path>>(resource_dir: P) -> ResourceDir {
 ///
 /// A builder structure allows to change default settings for:
 /// - file filter
+/// - include and exclude globs of keys
+/// - required keys
+/// - duplicate content check
+/// - skipping and renaming of resources
+/// - merged resource directories
+/// - resolution of resources with the same key
 /// - generated file name
 /// - generated function name
-#[derive(Default)]
+/// - set module naming
+/// - split of set modules by size or top-level directories
+/// - flat generation of few resources
+/// - formatting of generated code
+/// - relative paths of included files
+/// - hasher of generated map
+/// - preload hints of HTML resources
+/// - namespace of resource type
+/// - additional imports of generated modules
+/// - parallel writing of set modules
+/// - captured extended attributes
+/// - download names of resources
+/// - `Vary` header hints of resources
+/// - aliases of versioned keys
+/// - order of resources
+/// - appending to generated file
+/// - inlining of small files
+/// - conditional compilation of resources
+/// - immutability of resources
+/// - Unicode normalization of keys
+/// - custom attributes of resources
+/// - warnings about large resources
+/// - charset of text resources
+/// - bundle type of generated function
+/// - manifest and tree hashes of resources
+/// - JSON manifest of resources
+/// - delta of resources changed since a previous manifest
+/// - `ETag` of resources
+/// - Subresource Integrity metadata of resources
+/// - Unix permissions of resources
+/// - `matchit` router function
+/// - modification times of resources, clamped to `SOURCE_DATE_EPOCH` if set
+/// - change detection instructions of resource directories
+/// - key prefix argument of generated function
+/// - conditional compilation of generated function
+/// - constant listing keys
+/// - mime types of file extensions
+#[derive(Clone, Default)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct ResourceDir {
     pub(crate) resource_dir: PathBuf,
-    pub(crate) filter: Option<fn(p: &Path) -> bool>,
+    pub(crate) filter: Option<Filter>,
+    pub(crate) include_globs: Vec<String>,
+    pub(crate) exclude_globs: Vec<String>,
+    pub(crate) required_keys: Vec<String>,
+    pub(crate) forbid_duplicates: bool,
+    pub(crate) entry_action: Option<EntryActionFn>,
+    pub(crate) merged_dirs: Vec<(PathBuf, Option<Filter>)>,
+    pub(crate) key_conflict: KeyConflict,
     pub(crate) generated_filename: Option<PathBuf>,
     pub(crate) generated_fn: Option<String>,
     pub(crate) module_name: Option<String>,
     pub(crate) count_per_module: Option<usize>,
+    pub(crate) flat_threshold: usize,
+    pub(crate) max_bytes_per_module: Option<u64>,
+    pub(crate) parallel_modules: Option<usize>,
+    pub(crate) set_module_naming: Option<SetModuleNaming>,
+    pub(crate) rustfmt: bool,
+    pub(crate) relative_includes: bool,
+    pub(crate) key_hasher: KeyHasher,
+    pub(crate) preload_hints: bool,
+    pub(crate) resource_namespace: Option<String>,
+    #[cfg(feature = "parallel")]
+    pub(crate) sequential: bool,
+    #[cfg(feature = "xattr")]
+    pub(crate) xattrs: Vec<String>,
+    pub(crate) download_names: bool,
+    pub(crate) vary_hints: bool,
+    pub(crate) query_aliases: bool,
+    pub(crate) order: bool,
+    pub(crate) charset: bool,
+    pub(crate) append: bool,
+    pub(crate) inline_threshold: u64,
+    pub(crate) cfg: Option<fn(key: &str) -> Option<String>>,
+    pub(crate) immutable: Option<fn(file_name: &str) -> bool>,
+    #[cfg(feature = "unicode")]
+    pub(crate) unicode_normalization: Option<NormalizationForm>,
+    pub(crate) resource_builder: Option<Arc<dyn ResourceBuilder>>,
+    pub(crate) size_warning: Option<SizeWarning>,
+    pub(crate) bundle_type: Option<String>,
+    pub(crate) prefix_argument: bool,
+    pub(crate) fn_cfg: Option<String>,
+    pub(crate) module_uses: Vec<String>,
+    pub(crate) keys_const: bool,
+    pub(crate) modified_manifest: Option<PathBuf>,
+    pub(crate) fixed_modified: Option<u64>,
+    pub(crate) mime_overrides: HashMap<String, String>,
+    #[cfg(feature = "integrity")]
+    pub(crate) manifest_hash: bool,
+    #[cfg(feature = "integrity")]
+    pub(crate) tree_hash: bool,
+    #[cfg(feature = "manifest")]
+    pub(crate) json_manifest: Option<PathBuf>,
+    #[cfg(feature = "manifest")]
+    pub(crate) base_manifest: Option<PathBuf>,
+    #[cfg(feature = "integrity")]
+    pub(crate) etags: bool,
+    #[cfg(feature = "integrity")]
+    pub(crate) sri: bool,
+    pub(crate) modes: bool,
+    #[cfg(feature = "matchit")]
+    pub(crate) router_fn: Option<String>,
+    #[cfg(feature = "change-detection")]
+    pub(crate) change_detection: bool,
 }
 
+/// Returns whether the file or directory is collected.
+pub(crate) type Filter = Arc<dyn Fn(&Path) -> bool + Send + Sync>;
+
 pub const DEFAULT_MODULE_NAME: &str = "sets";
 pub const DEFAULT_COUNT_PER_MODULE: usize = 256;
 
 impl ResourceDir {
     /// Generates resources for current configuration.
     ///
+    /// Generated file and set modules inside the resource directory are not collected,
+    /// so the output of a previous build is never embedded.
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    /// use static_files::resource_dir;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let web_dir = Path::new(&out_dir).join("nested");
+    /// let _ = fs::remove_dir_all(&web_dir);
+    /// fs::create_dir_all(&web_dir).unwrap();
+    /// fs::write(web_dir.join("index.html"), "").unwrap();
+    ///
+    /// for _ in 0..2 {
+    ///     let mut resources = resource_dir(&web_dir);
+    ///     resources
+    ///         .with_generated_filename(web_dir.join("generated.rs"))
+    ///         .with_generated_fn("nested");
+    ///     resources.build().unwrap();
+    /// }
+    ///
+    /// let set = fs::read_to_string(web_dir.join("nested_sets/set_1.rs")).unwrap();
+    /// assert!(set.contains("\"index.html\""));
+    /// assert!(!set.contains("\"generated.rs\""));
+    /// assert!(!set.contains("nested_sets/"));
+    /// ```
+    ///
     /// # Panics
     /// Panics if `OUT_DIR` environment variable is not set.
     pub fn build(self) -> io::Result<()> {
-        let generated_filename = self.generated_filename.unwrap_or_else(|| {
+        #[cfg(feature = "change-detection")]
+        if self.change_detection {
+            self.generate_change_detection();
+        }
+
+        let (generated_filename, generated_fn, module_name) = self.generated_names();
+
+        let count_per_module = self.count_per_module.unwrap_or(DEFAULT_COUNT_PER_MODULE);
+        let mut set_split_strategy: Box<dyn SetSplitStrategie> =
+            match (self.parallel_modules, self.max_bytes_per_module) {
+                (Some(modules), _) => Box::new(SplitByTopDirHash::new(modules)),
+                (None, Some(max_bytes)) => Box::new(SplitBySize::new(max_bytes)),
+                (None, None) => Box::new(SplitByCount::new(count_per_module)),
+            };
+
+        let modified = match &self.modified_manifest {
+            Some(manifest) => read_modified_manifest(manifest)?,
+            None => HashMap::new(),
+        };
+
+        generate_resources_sets_with_options(
+            &self.resource_dir,
+            self.filter.as_deref(),
+            &generated_filename,
+            module_name.as_str(),
+            &generated_fn,
+            set_split_strategy.as_mut(),
+            &SetsOptions {
+                set_module_naming: self.set_module_naming.unwrap_or_default(),
+                merged_dirs: self.merged_dirs,
+                relative_includes: self.relative_includes,
+                key_hasher: self.key_hasher,
+                append: self.append,
+                insert: InsertOptions {
+                    preload_hints: self.preload_hints,
+                    download_names: self.download_names,
+                    vary_hints: self.vary_hints,
+                    query_aliases: self.query_aliases,
+                    order: self.order,
+                    charset: self.charset,
+                    inline_threshold: self.inline_threshold,
+                    cfg: self.cfg,
+                    immutable: self.immutable,
+                    #[cfg(feature = "xattr")]
+                    xattrs: self.xattrs,
+                    resource_namespace: self
+                        .resource_namespace
+                        .unwrap_or_else(|| DEFAULT_RESOURCE_NAMESPACE.into()),
+                    #[cfg(feature = "unicode")]
+                    unicode_normalization: self.unicode_normalization,
+                    #[cfg(feature = "integrity")]
+                    etags: self.etags,
+                    #[cfg(feature = "integrity")]
+                    sri: self.sri,
+                    modes: self.modes,
+                    modified,
+                    fixed_modified: self.fixed_modified,
+                    source_date_epoch: source_date_epoch(),
+                    mime_overrides: self.mime_overrides,
+                    resource_builder: self.resource_builder,
+                    renamed: HashMap::new(),
+                    #[cfg(feature = "manifest")]
+                    manifest: self.json_manifest.as_ref().map(|_| Arc::default()),
+                },
+                #[cfg(feature = "parallel")]
+                sequential: self.sequential,
+                size_warning: self.size_warning,
+                include_globs: self.include_globs,
+                exclude_globs: self.exclude_globs,
+                required_keys: self.required_keys,
+                forbid_duplicates: self.forbid_duplicates,
+                key_conflict: self.key_conflict,
+                entry_action: self.entry_action,
+                bundle_type: self.bundle_type,
+                prefix_argument: self.prefix_argument,
+                fn_cfg: self.fn_cfg,
+                module_uses: self.module_uses,
+                flat_threshold: self.flat_threshold,
+                keys_const: self.keys_const,
+                #[cfg(feature = "integrity")]
+                manifest_hash: self.manifest_hash,
+                #[cfg(feature = "integrity")]
+                tree_hash: self.tree_hash,
+                #[cfg(feature = "manifest")]
+                json_manifest: self.json_manifest,
+                #[cfg(feature = "manifest")]
+                base_manifest: self.base_manifest,
+                #[cfg(feature = "matchit")]
+                router_fn: self.router_fn,
+            },
+        )?;
+
+        if self.rustfmt {
+            rustfmt(
+                &generated_filename,
+                &module_dir(&generated_filename, &module_name),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Returns the generated file, the generated function and the module name of sets.
+    ///
+    /// # Panics
+    /// Panics if the generated file is not set and `OUT_DIR` environment variable is not set.
+    pub(crate) fn generated_names(&self) -> (PathBuf, String, String) {
+        let generated_filename = self.generated_filename.clone().unwrap_or_else(|| {
             let out_dir = env::var("OUT_DIR").unwrap();
 
             Path::new(&out_dir).join("generated.rs")
         });
-        let generated_fn = self.generated_fn.unwrap_or_else(|| "generate".into());
+        let generated_fn = self
+            .generated_fn
+            .clone()
+            .unwrap_or_else(|| "generate".into());
 
         let module_name = self
             .module_name
+            .clone()
             .unwrap_or_else(|| format!("{generated_fn}_{DEFAULT_MODULE_NAME}"));
 
-        let count_per_module = self.count_per_module.unwrap_or(DEFAULT_COUNT_PER_MODULE);
+        (generated_filename, generated_fn, module_name)
+    }
 
-        generate_resources_sets(
-            &self.resource_dir,
-            self.filter,
-            generated_filename,
-            module_name.as_str(),
-            &generated_fn,
-            &mut SplitByCount::new(count_per_module),
-        )
+    /// Generates change detection instructions for the resource directory and merged ones.
+    #[cfg(feature = "change-detection")]
+    fn generate_change_detection(&self) {
+        let change_detection = self.merged_dirs.iter().fold(
+            ::change_detection::ChangeDetection::path(&self.resource_dir),
+            |change_detection, (dir, _)| change_detection.path(dir),
+        );
+        change_detection.generate();
+        println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    }
+
+    /// Sets the file filter, files and directories are collected if it returns `true`.
+    ///
+    /// The filter can capture its environment, like extensions read from the build configuration.
+    ///
+    /// ```rust
+    /// include!(concat!(env!("OUT_DIR"), "/generated_filter.rs"));
+    ///
+    /// fn main() {
+    ///     let mut keys = generate_filter().into_keys().collect::<Vec<_>>();
+    ///     keys.sort_unstable();
+    ///     assert_eq!(keys, ["file1.txt", "file2.txt"]);
+    /// }
+    /// ```
+    pub fn with_filter<F>(&mut self, filter: F) -> &mut Self
+    where
+        F: Fn(&Path) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Collects only files with keys matching any of glob `patterns` (default is all files).
+    ///
+    /// Globs match keys, with `/` separators on every platform, like [`glob`](crate::resource::glob).
+    /// Building fails if a pattern uses syntax [`glob`](crate::resource::glob) doesn't support.
+    /// Unlike [`ResourceDir::with_filter`], patterns can come from the build configuration.
+    ///
+    /// ```rust
+    /// include!(concat!(env!("OUT_DIR"), "/generated_globs.rs"));
+    ///
+    /// fn main() {
+    ///     let mut keys = generate_globs().into_keys().collect::<Vec<_>>();
+    ///     keys.sort_unstable();
+    ///     assert_eq!(keys, ["file1.txt", "file3.info"]);
+    /// }
+    /// ```
+    pub fn with_include_globs<I, S>(&mut self, patterns: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.include_globs = patterns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Skips files with keys matching any of glob `patterns` (default is none),
+    /// also when they match include globs.
+    ///
+    /// See [`ResourceDir::with_include_globs`].
+    ///
+    /// ```rust
+    /// use std::{env, path::Path};
+    /// use static_files::resource_dir;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let mut resources = resource_dir("./tests");
+    /// resources
+    ///     .with_generated_filename(Path::new(&out_dir).join("generated_exclude_globs.rs"))
+    ///     .with_exclude_globs(["*.{html,info}"]);
+    ///
+    /// let err = resources.build().unwrap_err();
+    /// assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    /// ```
+    pub fn with_exclude_globs<I, S>(&mut self, patterns: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.exclude_globs = patterns.into_iter().map(Into::into).collect();
+        self
     }
 
-    /// Sets the file filter.
-    pub fn with_filter(&mut self, filter: fn(p: &Path) -> bool) -> &mut Self {
-        self.filter = Some(filter);
+    /// Sets the function deciding for every collected file whether it is skipped,
+    /// kept or renamed (default is none).
+    ///
+    /// It combines filtering and renaming of keys in one decision, based on the file
+    /// and its content. Globs and required keys are checked against renamed keys.
+    /// Unlike [`ResourceDir::with_filter`], it is not called for directories.
+    ///
+    /// ```rust
+    /// include!(concat!(env!("OUT_DIR"), "/generated_entry_action.rs"));
+    ///
+    /// fn main() {
+    ///     let resources = generate_entry_action();
+    ///
+    ///     let mut keys = resources.keys().copied().collect::<Vec<_>>();
+    ///     keys.sort_unstable();
+    ///     // `file2.txt` is skipped, `file3.info` is renamed.
+    ///     assert_eq!(keys, ["docs/file3.info", "file1.txt", "index.html"]);
+    /// }
+    /// ```
+    ///
+    /// The build fails if a renamed key is the key of another resource:
+    ///
+    /// ```rust
+    /// use std::{env, path::Path};
+    /// use static_files::{resource::EntryAction, resource_dir};
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let mut resources = resource_dir("./tests");
+    /// resources
+    ///     .with_generated_filename(Path::new(&out_dir).join("generated_renamed_twice.rs"))
+    ///     .with_entry_action(|file| match file.key {
+    ///         "file2.txt" => EntryAction::Rename("file1.txt".into()),
+    ///         _ => EntryAction::Keep,
+    ///     });
+    ///
+    /// let err = resources.build().unwrap_err();
+    /// assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    /// assert!(err.to_string().contains("file1.txt"));
+    /// assert!(err.to_string().contains("file2.txt"));
+    /// ```
+    pub fn with_entry_action<F>(&mut self, entry_action: F) -> &mut Self
+    where
+        F: Fn(&FileContext) -> EntryAction + Send + Sync + 'static,
+    {
+        self.entry_action = Some(Arc::new(entry_action));
+        self
+    }
+
+    /// Fails the build if resources with any of `keys` are not collected (default is none).
+    ///
+    /// Use it for critical resources, like `index.html`, to catch a broken bundler output
+    /// at compile time. Keys are checked after filters, globs and Unicode normalization.
+    ///
+    /// ```rust
+    /// use std::{env, path::Path};
+    /// use static_files::resource_dir;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let mut resources = resource_dir("./tests");
+    /// resources
+    ///     .with_generated_filename(Path::new(&out_dir).join("generated_required.rs"))
+    ///     .with_include_globs(["*.txt"])
+    ///     .with_required_keys(["file1.txt", "index.html"]);
+    ///
+    /// let err = resources.build().unwrap_err();
+    /// assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    /// assert!(err.to_string().contains("[\"index.html\"]"));
+    /// ```
+    pub fn with_required_keys<I, S>(&mut self, keys: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.required_keys = keys.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Fails the build if several resources have the same content (default is `false`).
+    ///
+    /// Use it when duplicates indicate a bug, like a chunk emitted twice by a bundler.
+    /// The error lists keys of each group of duplicates.
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    /// use static_files::resource_dir;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let out_dir = Path::new(&out_dir);
+    /// let duplicates_dir = out_dir.join("duplicates");
+    /// fs::create_dir_all(&duplicates_dir).unwrap();
+    /// fs::write(duplicates_dir.join("chunk-1a2b3c.js"), "chunk").unwrap();
+    /// fs::write(duplicates_dir.join("chunk-4d5e6f.js"), "chunk").unwrap();
+    /// fs::write(duplicates_dir.join("other-7a8b9c.js"), "other").unwrap();
+    ///
+    /// let mut resources = resource_dir(&duplicates_dir);
+    /// resources
+    ///     .with_generated_filename(out_dir.join("generated_duplicates.rs"))
+    ///     .with_forbid_duplicates(true);
+    ///
+    /// let err = resources.build().unwrap_err();
+    /// assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    /// assert!(err
+    ///     .to_string()
+    ///     .contains("[[\"chunk-1a2b3c.js\", \"chunk-4d5e6f.js\"]]"));
+    /// ```
+    pub fn with_forbid_duplicates(&mut self, forbid_duplicates: bool) -> &mut Self {
+        self.forbid_duplicates = forbid_duplicates;
+        self
+    }
+
+    /// Merges resources of `other` directory, like the target of another [`NpmBuild`](crate::NpmBuild),
+    /// with keys relative to it (default is none).
+    ///
+    /// Only the directory and the filter of `other` are used, resources get the options of `self`.
+    /// The build fails if a key of `other` is the key of another resource,
+    /// unless [`ResourceDir::with_key_conflict`] selects the embedded one.
+    ///
+    /// ```rust
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::{env, fs, path::Path};
+    /// use static_files::{NpmBuild, ResourceDir};
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let out_dir = Path::new(&out_dir);
+    ///
+    /// // Fake package manager writing its name to `dist/SCRIPT.txt`.
+    /// let npm_build = |executable: &str, script: &str| -> ResourceDir {
+    ///     let package_dir = out_dir.join("npm_merged").join(executable);
+    ///     let _ = fs::remove_dir_all(&package_dir);
+    ///     fs::create_dir_all(&package_dir).unwrap();
+    ///
+    ///     NpmBuild::new(&package_dir)
+    ///         .executable(out_dir.join("fake_npm").join(executable).to_str().unwrap())
+    ///         .install()
+    ///         .unwrap()
+    ///         .run(script)
+    ///         .unwrap()
+    ///         .target(package_dir.join("dist"))
+    ///         .into()
+    /// };
+    ///
+    /// let mut resources = npm_build("pnpm", "admin");
+    /// resources
+    ///     .with_merged(npm_build("yarn", "shop"))
+    ///     .with_generated_filename(out_dir.join("generated_npm_merged.rs"))
+    ///     .with_generated_fn("npm_merged");
+    /// resources.build().unwrap();
+    ///
+    /// let set = fs::read_to_string(out_dir.join("npm_merged_sets/set_1.rs")).unwrap();
+    /// assert!(set.contains("\"admin.txt\""));
+    /// assert!(set.contains("\"shop.txt\""));
+    ///
+    /// let mut resources = npm_build("pnpm", "index");
+    /// resources
+    ///     .with_merged(npm_build("yarn", "index"))
+    ///     .with_generated_filename(out_dir.join("generated_npm_collision.rs"));
+    /// let err = resources.build().unwrap_err();
+    /// assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    /// assert!(err.to_string().contains("\"index.txt\""));
+    /// # }
+    /// ```
+    pub fn with_merged<R: Into<ResourceDir>>(&mut self, other: R) -> &mut Self {
+        let other = other.into();
+        self.merged_dirs.push((other.resource_dir, other.filter));
+        self.merged_dirs.extend(other.merged_dirs);
+        self
+    }
+
+    /// Sets which resource is embedded when several ones have the same key
+    /// (default is [`KeyConflict::Error`]).
+    ///
+    /// Keys of merged directories often collide, like `index.html` of several builds.
+    /// By default the build fails naming paths of both resources.
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    /// use static_files::{resource_dir, sets::KeyConflict};
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let out_dir = Path::new(&out_dir);
+    /// let mut dirs = vec![];
+    /// for name in ["conflict_first", "conflict_last"] {
+    ///     let dir = out_dir.join(name);
+    ///     fs::create_dir_all(&dir).unwrap();
+    ///     fs::write(dir.join("index.html"), name).unwrap();
+    ///     dirs.push(dir);
+    /// }
+    ///
+    /// let mut resources = resource_dir(&dirs[0]);
+    /// resources
+    ///     .with_merged(resource_dir(&dirs[1]))
+    ///     .with_generated_filename(out_dir.join("generated_key_conflict.rs"))
+    ///     .with_generated_fn("key_conflict");
+    ///
+    /// let err = resources.clone().build().unwrap_err();
+    /// assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    /// for dir in &dirs {
+    ///     let path = dir.join("index.html");
+    ///     assert!(err.to_string().contains(&path.display().to_string()));
+    /// }
+    ///
+    /// resources.with_key_conflict(KeyConflict::LastWins);
+    /// resources.clone().build().unwrap();
+    /// let set = fs::read_to_string(out_dir.join("key_conflict_sets/set_1.rs")).unwrap();
+    /// assert!(set.contains("conflict_last") && !set.contains("conflict_first"));
+    ///
+    /// resources.with_key_conflict(KeyConflict::FirstWins);
+    /// resources.build().unwrap();
+    /// let set = fs::read_to_string(out_dir.join("key_conflict_sets/set_1.rs")).unwrap();
+    /// assert!(set.contains("conflict_first") && !set.contains("conflict_last"));
+    /// ```
+    pub fn with_key_conflict(&mut self, key_conflict: KeyConflict) -> &mut Self {
+        self.key_conflict = key_conflict;
         self
     }
 
@@ -108,4 +673,1358 @@ impl ResourceDir {
         self.count_per_module = Some(count_per_module);
         self
     }
+
+    /// Generates fewer than `flat_threshold` resources in one function inlined into
+    /// the generated file, without the directory of set modules (default is `0`, always split).
+    ///
+    /// Use it to keep the output of small projects tidy, while large ones are still split.
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    /// use static_files::resource_dir;
+    ///
+    /// include!(concat!(env!("OUT_DIR"), "/generated_flat.rs"));
+    ///
+    /// fn main() {
+    ///     assert_eq!(generate_flat().len(), 4);
+    ///
+    ///     let out_dir = Path::new(&env::var("OUT_DIR").unwrap()).join("flat");
+    ///     for count in [3, 500] {
+    ///         let resources_dir = out_dir.join(format!("resources_{count}"));
+    ///         fs::create_dir_all(&resources_dir).unwrap();
+    ///         for index in 0..count {
+    ///             fs::write(resources_dir.join(format!("{index}.txt")), "").unwrap();
+    ///         }
+    ///
+    ///         let generated_fn = format!("flat_{count}");
+    ///         let mut resources = resource_dir(&resources_dir);
+    ///         resources
+    ///             .with_generated_filename(out_dir.join(format!("generated_{count}.rs")))
+    ///             .with_generated_fn(&generated_fn)
+    ///             .with_flat_threshold(100);
+    ///         resources.build().unwrap();
+    ///
+    ///         let module_dir = out_dir.join(format!("{generated_fn}_sets"));
+    ///         assert_eq!(module_dir.exists(), count >= 100);
+    ///     }
+    /// }
+    /// ```
+    pub fn with_flat_threshold(&mut self, flat_threshold: usize) -> &mut Self {
+        self.flat_threshold = flat_threshold;
+        self
+    }
+
+    /// Splits files into set modules by their total size in bytes, instead of by count of files
+    /// (see [`ResourceDir::with_count_per_module`]).
+    ///
+    /// Use it when sizes of files vary a lot, so large modules don't exhaust memory of `rustc`.
+    /// A module is closed once it reaches `max_bytes`, see [`SplitBySize`].
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    ///
+    /// include!(concat!(env!("OUT_DIR"), "/generated_sizes.rs"));
+    ///
+    /// fn main() {
+    ///     assert_eq!(generate_sizes().len(), 4);
+    ///
+    ///     let sets_dir = Path::new(&env::var("OUT_DIR").unwrap()).join("generate_sizes_sets");
+    ///     let set_1 = fs::read_to_string(sets_dir.join("set_1.rs")).unwrap();
+    ///     assert!(set_1.contains("\"a.bin\"") && set_1.contains("\"b.bin\""));
+    ///     let set_2 = fs::read_to_string(sets_dir.join("set_2.rs")).unwrap();
+    ///     assert!(set_2.contains("\"c.bin\"") && set_2.contains("\"d.bin\""));
+    ///     assert!(!sets_dir.join("set_3.rs").exists());
+    /// }
+    /// ```
+    pub fn with_max_bytes_per_module(&mut self, max_bytes: u64) -> &mut Self {
+        self.max_bytes_per_module = Some(max_bytes);
+        self
+    }
+
+    /// Splits files into `modules` set modules by hash of their top-level directory,
+    /// instead of by count of files (see [`ResourceDir::with_count_per_module`]).
+    ///
+    /// Adding a file changes only the module of its top-level directory, so other modules
+    /// are not recompiled, and `rustc` compiles the modules in parallel. Grouping by directory
+    /// would give one module per directory, hashing caps their number at `modules`,
+    /// but a module is as large as the directories hashed to it. See [`SplitByTopDirHash`].
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    ///
+    /// include!(concat!(env!("OUT_DIR"), "/generated_top_dirs.rs"));
+    ///
+    /// fn main() {
+    ///     assert_eq!(generate_top_dirs().len(), 6);
+    ///
+    ///     let out_dir = env::var("OUT_DIR").unwrap();
+    ///     let sets = (1..=3)
+    ///         .map(|index| {
+    ///             let set = Path::new(&out_dir).join(format!("generate_top_dirs_sets/set_{index}.rs"));
+    ///             fs::read_to_string(set).unwrap()
+    ///         })
+    ///         .collect::<Vec<_>>();
+    ///     assert!(!Path::new(&out_dir).join("generate_top_dirs_sets/set_4.rs").exists());
+    ///
+    ///     let set_of = |key: &str| {
+    ///         sets.iter()
+    ///             .position(|set| set.contains(&format!("{key:?}")))
+    ///             .unwrap()
+    ///     };
+    ///     assert_eq!(set_of("css/app.css"), set_of("css/theme.css"));
+    ///     assert_eq!(set_of("js/app.js"), set_of("js/vendor/lib.js"));
+    /// }
+    /// ```
+    pub fn with_parallel_modules(&mut self, modules: usize) -> &mut Self {
+        self.parallel_modules = Some(modules);
+        self
+    }
+
+    /// Sets the prefix of set module names (default is `set_`).
+    ///
+    /// Set modules are named as the prefix followed by the module index.
+    /// Use it to avoid clashes with existing modules in the include target.
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    /// use static_files::resource_dir;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let mut resources = resource_dir("./tests");
+    /// resources
+    ///     .with_generated_filename(Path::new(&out_dir).join("generated_chunks.rs"))
+    ///     .with_generated_fn("chunks")
+    ///     .with_count_per_module(2)
+    ///     .with_set_module_prefix("chunk_");
+    /// resources.build().unwrap();
+    ///
+    /// let module_dir = Path::new(&out_dir).join("chunks_sets");
+    /// assert!(module_dir.join("chunk_1.rs").exists());
+    /// assert!(module_dir.join("chunk_2.rs").exists());
+    /// assert!(!module_dir.join("set_1.rs").exists());
+    ///
+    /// let module = fs::read_to_string(module_dir.join("mod.rs")).unwrap();
+    /// assert!(module.contains("mod chunk_1;"));
+    /// assert!(module.contains("chunk_2::generate(&mut r);"));
+    /// ```
+    pub fn with_set_module_prefix<S>(&mut self, prefix: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.set_module_naming = Some(SetModuleNaming::Prefix(prefix.into()));
+        self
+    }
+
+    /// Names set modules as the prefix followed by a hash of their resource keys.
+    ///
+    /// Set module names stay the same until their membership changes, which keeps
+    /// incremental compilation caches valid when unrelated resources are added or removed.
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    /// use static_files::resource_dir;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let web_dir = Path::new(&out_dir).join("content_hash");
+    /// let _ = fs::remove_dir_all(&web_dir);
+    /// fs::create_dir_all(&web_dir).unwrap();
+    /// fs::write(web_dir.join("a.txt"), "").unwrap();
+    ///
+    /// let set_modules = || {
+    ///     let mut resources = resource_dir(&web_dir);
+    ///     resources
+    ///         .with_generated_filename(Path::new(&out_dir).join("generated_content_hash.rs"))
+    ///         .with_generated_fn("content_hash")
+    ///         .with_count_per_module(1)
+    ///         .with_filter(|path| path.file_name().map_or(true, |name| name != "set_modules"))
+    ///         .with_set_module_content_hash("set_");
+    ///     resources.build().unwrap();
+    ///
+    ///     let module_dir = Path::new(&out_dir).join("content_hash_sets");
+    ///     let module = fs::read_to_string(module_dir.join("mod.rs")).unwrap();
+    ///     let mut set_modules = module
+    ///         .lines()
+    ///         .filter_map(|line| line.strip_prefix("mod "))
+    ///         .map(|name| name.trim_end_matches(';').to_owned())
+    ///         .collect::<Vec<_>>();
+    ///     for set_module in &set_modules {
+    ///         assert!(module_dir.join(format!("{set_module}.rs")).exists());
+    ///     }
+    ///     set_modules.sort();
+    ///     set_modules
+    /// };
+    ///
+    /// let before = set_modules();
+    /// assert_eq!(before.len(), 1);
+    /// assert!(before[0].starts_with("set_"));
+    ///
+    /// fs::write(web_dir.join("b.txt"), "").unwrap();
+    /// let after = set_modules();
+    /// assert_eq!(after.len(), 2);
+    /// assert!(after.contains(&before[0]));
+    /// ```
+    pub fn with_set_module_content_hash<S>(&mut self, prefix: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.set_module_naming = Some(SetModuleNaming::ContentHash(prefix.into()));
+        self
+    }
+
+    /// Sets the function producing set module names from the module index (starting from 1).
+    ///
+    /// Produced names must be valid and unique identifiers, otherwise `build` fails.
+    pub fn with_set_module_naming(&mut self, naming: fn(usize) -> String) -> &mut Self {
+        self.set_module_naming = Some(SetModuleNaming::Func(naming));
+        self
+    }
+
+    /// Formats generated files with `rustfmt` (default is `false`).
+    ///
+    /// Use it to inspect generated code. Formatting is skipped silently if
+    /// `rustfmt` is not available or fails. It is slow for large generated files.
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path, process::Command};
+    /// use static_files::resource_dir;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let out_dir = Path::new(&out_dir);
+    ///
+    /// for (generated_fn, rustfmt) in [("plain", false), ("formatted", true)] {
+    ///     let mut resources = resource_dir("./tests");
+    ///     resources
+    ///         .with_generated_filename(out_dir.join(format!("generated_{generated_fn}.rs")))
+    ///         .with_generated_fn(generated_fn)
+    ///         .with_rustfmt(rustfmt);
+    ///     resources.build().unwrap();
+    /// }
+    ///
+    /// let plain = fs::read_to_string(out_dir.join("plain_sets/mod.rs")).unwrap();
+    /// let formatted = fs::read_to_string(out_dir.join("formatted_sets/mod.rs")).unwrap();
+    ///
+    /// if Command::new("rustfmt").arg("--version").output().is_ok() {
+    ///     assert_ne!(plain.replace("plain", "formatted"), formatted);
+    /// }
+    /// ```
+    pub fn with_rustfmt(&mut self, rustfmt: bool) -> &mut Self {
+        self.rustfmt = rustfmt;
+        self
+    }
+
+    /// Includes files with paths relative to the generated module (default is `false`).
+    ///
+    /// By default included files have absolute paths, which makes generated code
+    /// specific to the build machine. Relative paths keep it relocatable and
+    /// friendly to build caches. Resources and generated code must be on the same drive.
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    ///
+    /// include!(concat!(env!("OUT_DIR"), "/generated_relative.rs"));
+    ///
+    /// fn main() {
+    ///     let resources = generate_relative();
+    ///     assert_eq!(resources.len(), 4);
+    ///
+    ///     let resources_dir = fs::canonicalize("./tests").unwrap();
+    ///     let module_dir = Path::new(env!("OUT_DIR")).join("generate_relative_sets");
+    ///     for entry in fs::read_dir(module_dir).unwrap() {
+    ///         let module = fs::read_to_string(entry.unwrap().path()).unwrap();
+    ///         assert!(!module.contains(resources_dir.to_str().unwrap()));
+    ///     }
+    /// }
+    /// ```
+    pub fn with_relative_includes(&mut self, relative_includes: bool) -> &mut Self {
+        self.relative_includes = relative_includes;
+        self
+    }
+
+    /// Sets the hasher of generated map (default is [`KeyHasher::SipHash`]).
+    pub fn with_key_hasher(&mut self, key_hasher: KeyHasher) -> &mut Self {
+        self.key_hasher = key_hasher;
+        self
+    }
+
+    /// Collects preload hints of HTML resources (default is `false`).
+    ///
+    /// Resources referenced by `<script src>`, `<link rel=stylesheet>` and `<link rel=preload>`
+    /// are stored in [`Resource::preload`](crate::Resource::preload).
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    /// use static_files::resource_dir;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let web_dir = Path::new(&out_dir).join("preload");
+    /// fs::create_dir_all(&web_dir).unwrap();
+    /// fs::write(
+    ///     web_dir.join("index.html"),
+    ///     r#"<html><head>
+    ///     <link rel="stylesheet" href="/app.css">
+    ///     <link rel=preload href="/font.woff2" as=font crossorigin>
+    ///     <link rel="icon" href="/favicon.ico">
+    ///     <!-- <script src="/commented.js"></script> -->
+    ///     <script type="module" src='/app.js'></script>
+    ///     </head></html>"#,
+    /// )
+    /// .unwrap();
+    ///
+    /// let mut resources = resource_dir(&web_dir);
+    /// resources
+    ///     .with_generated_filename(Path::new(&out_dir).join("generated_preload.rs"))
+    ///     .with_generated_fn("preload")
+    ///     .with_preload_hints(true);
+    /// resources.build().unwrap();
+    ///
+    /// let set = fs::read_to_string(Path::new(&out_dir).join("preload_sets/set_1.rs")).unwrap();
+    /// assert!(set.contains(
+    ///     ".with_preload(&[\
+    ///     ::static_files::resource::PreloadHint{href:\"/app.css\",destination:\"style\"},\
+    ///     ::static_files::resource::PreloadHint{href:\"/font.woff2\",destination:\"font\"},\
+    ///     ::static_files::resource::PreloadHint{href:\"/app.js\",destination:\"script\"}])"
+    /// ));
+    /// ```
+    pub fn with_preload_hints(&mut self, preload_hints: bool) -> &mut Self {
+        self.preload_hints = preload_hints;
+        self
+    }
+
+    /// Stores base names of resource files as download names (default is `false`).
+    ///
+    /// Base name is stored in [`Resource::download_name`](crate::Resource::download_name),
+    /// use it to set `Content-Disposition: attachment; filename=...` header.
+    ///
+    /// ```rust
+    /// include!(concat!(env!("OUT_DIR"), "/generated_download.rs"));
+    ///
+    /// fn main() {
+    ///     let resources = generate_download();
+    ///     assert_eq!(resources["file1.txt"].download_name, Some("file1.txt"));
+    ///     assert_eq!(resources["index.html"].download_name, Some("index.html"));
+    /// }
+    /// ```
+    pub fn with_download_names(&mut self, download_names: bool) -> &mut Self {
+        self.download_names = download_names;
+        self
+    }
+
+    /// Stores Unix permission bits of resource files (default is `false`).
+    ///
+    /// Mode is stored in [`Resource::mode`](crate::Resource::mode) and restored by
+    /// [`extract_all`](crate::resource::extract_all), use it for executables of embedded file systems.
+    /// Nothing is stored on other platforms.
+    ///
+    /// ```rust
+    /// # #[cfg(unix)]
+    /// include!(concat!(env!("OUT_DIR"), "/generated_modes.rs"));
+    ///
+    /// fn main() {
+    /// #   #[cfg(unix)]
+    /// #   {
+    ///     let resources = generate_modes();
+    ///     assert_eq!(resources["bin/init"].mode, Some(0o755));
+    ///     assert_eq!(resources["etc/hostname"].mode, Some(0o644));
+    /// #   }
+    /// }
+    /// ```
+    pub fn with_modes(&mut self, modes: bool) -> &mut Self {
+        self.modes = modes;
+        self
+    }
+
+    /// Computes `Vary` header hints of resources from their variants (default is `false`).
+    ///
+    /// Hints are stored in [`Resource::vary`](crate::Resource::vary): `Accept-Encoding`
+    /// for files with precompressed variants (`.br`, `.gz` or `.zst` appended)
+    /// and `Accept` for images with variants of the same name in other image formats.
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    /// use static_files::resource_dir;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let web_dir = Path::new(&out_dir).join("vary");
+    /// let _ = fs::remove_dir_all(&web_dir);
+    /// fs::create_dir_all(&web_dir).unwrap();
+    /// for file in ["app.js", "app.js.gz", "app.js.br", "photo.jpg", "photo.avif", "index.html"] {
+    ///     fs::write(web_dir.join(file), "").unwrap();
+    /// }
+    ///
+    /// let mut resources = resource_dir(&web_dir);
+    /// resources
+    ///     .with_generated_filename(Path::new(&out_dir).join("generated_vary.rs"))
+    ///     .with_generated_fn("vary")
+    ///     .with_vary_hints(true);
+    /// resources.build().unwrap();
+    ///
+    /// let set = fs::read_to_string(Path::new(&out_dir).join("vary_sets/set_1.rs")).unwrap();
+    /// let insert = |key: &str| {
+    ///     set.lines()
+    ///         .find(|line| line.contains(&format!("insert({key:?},")))
+    ///         .unwrap()
+    ///         .to_owned()
+    /// };
+    /// assert!(insert("app.js").ends_with(".with_vary(\"Accept-Encoding\"));"));
+    /// assert!(insert("photo.jpg").ends_with(".with_vary(\"Accept\"));"));
+    /// assert!(!insert("index.html").contains("with_vary"));
+    /// ```
+    pub fn with_vary_hints(&mut self, vary_hints: bool) -> &mut Self {
+        self.vary_hints = vary_hints;
+        self
+    }
+
+    /// Registers files with `?`-suffixed names under their base names too (default is `false`).
+    ///
+    /// A file named like `app.js?v=123` by a bundler is available with both `app.js?v=123`
+    /// and `app.js` keys. A file named exactly as the base name takes precedence.
+    /// Names legitimately containing `?` are split the same way, don't enable it for them.
+    ///
+    /// ```rust
+    /// # #[cfg(unix)]
+    /// include!(concat!(env!("OUT_DIR"), "/generated_query.rs"));
+    ///
+    /// fn main() {
+    /// #   #[cfg(unix)]
+    /// #   {
+    ///     let resources = generate_query();
+    ///     assert_eq!(resources.len(), 4);
+    ///     assert_eq!(resources["app.js"].data, b"versioned");
+    ///     assert_eq!(resources["app.js?v=123"].data, b"versioned");
+    ///     assert_eq!(resources["style.css"].data, b"exact");
+    ///     assert_eq!(resources["style.css?v=1"].data, b"versioned");
+    /// #   }
+    /// }
+    /// ```
+    pub fn with_query_aliases(&mut self, query_aliases: bool) -> &mut Self {
+        self.query_aliases = query_aliases;
+        self
+    }
+
+    /// Stores positions of resources sorted by key (default is `false`).
+    ///
+    /// Positions are stored in [`Resource::order`](crate::Resource::order), use them
+    /// to process resources in a deterministic order regardless of map iteration order.
+    /// Resources are also split into set modules in this order.
+    ///
+    /// ```rust
+    /// include!(concat!(env!("OUT_DIR"), "/generated_order.rs"));
+    ///
+    /// fn main() {
+    ///     let resources = generate_order();
+    ///
+    ///     let mut ordered = resources.iter().collect::<Vec<_>>();
+    ///     ordered.sort_by_key(|(_, resource)| resource.order);
+    ///
+    ///     let orders = ordered.iter().map(|(_, resource)| resource.order).collect::<Vec<_>>();
+    ///     assert_eq!(orders, [0, 1, 2, 3]);
+    ///     let keys = ordered.iter().map(|(key, _)| **key).collect::<Vec<_>>();
+    ///     assert_eq!(keys, ["file1.txt", "file2.txt", "file3.info", "index.html"]);
+    /// }
+    /// ```
+    pub fn with_order(&mut self, order: bool) -> &mut Self {
+        self.order = order;
+        self
+    }
+
+    /// Appends charset to mime types of text resources, like `text/plain; charset=utf-8`
+    /// (default is `false`, so mime types of existing builds don't change).
+    ///
+    /// Text resources are UTF-8. With `charset` feature, the charset of text files
+    /// in other encodings, like legacy Latin-1 or Shift JIS files, is detected.
+    ///
+    /// ```rust
+    /// include!(concat!(env!("OUT_DIR"), "/generated_charset.rs"));
+    ///
+    /// fn main() {
+    ///     let resources = generate_charset();
+    ///     assert_eq!(resources["utf8.txt"].mime_type, "text/plain; charset=utf-8");
+    ///     assert_eq!(resources["image.png"].mime_type, "image/png");
+    ///
+    ///     let latin1 = if cfg!(feature = "charset") {
+    ///         "text/csv; charset=windows-1252"
+    ///     } else {
+    ///         "text/csv"
+    ///     };
+    ///     assert_eq!(resources["latin1.csv"].mime_type, latin1);
+    /// }
+    /// ```
+    pub fn with_charset(&mut self, charset: bool) -> &mut Self {
+        self.charset = charset;
+        self
+    }
+
+    /// Sets mime types of file extensions, overriding guessed ones (default is empty).
+    ///
+    /// Extensions are matched case-insensitively, with or without a leading dot.
+    /// Files with other extensions keep the guessed mime type. Parameters of mime types,
+    /// like `charset`, are kept and take precedence over [`ResourceDir::with_charset`].
+    ///
+    /// ```rust
+    /// include!(concat!(env!("OUT_DIR"), "/generated_mime.rs"));
+    ///
+    /// fn main() {
+    ///     let resources = generate_mime();
+    ///     assert_eq!(resources["file3.info"].mime_type, "text/x-info");
+    ///     assert_eq!(resources["index.html"].mime_type, "text/html; charset=utf-8");
+    ///     assert_eq!(resources["file1.txt"].mime_type, "text/plain");
+    /// }
+    /// ```
+    pub fn with_mime_overrides(&mut self, mime_overrides: HashMap<String, String>) -> &mut Self {
+        self.mime_overrides = mime_overrides
+            .into_iter()
+            .map(|(extension, mime_type)| {
+                (extension.trim_start_matches('.').to_lowercase(), mime_type)
+            })
+            .collect();
+        self
+    }
+
+    /// Appends generated function to the generated file instead of overwriting it (default is `false`).
+    ///
+    /// Use it to generate several functions, e.g. per application area, into one file.
+    /// The first generation into the file should not append, so rebuilds start from scratch.
+    /// Fails if the file already has a function or a module with the same name.
+    ///
+    /// ```rust
+    /// use std::{env, path::Path};
+    /// use static_files::resource_dir;
+    ///
+    /// include!(concat!(env!("OUT_DIR"), "/generated_multi.rs"));
+    ///
+    /// fn main() {
+    ///     assert_eq!(generate_app().len(), 4);
+    ///     assert_eq!(generate_docs().len(), 4);
+    ///
+    ///     let generated_filename = Path::new(env!("OUT_DIR")).join("generated_duplicate.rs");
+    ///     for append in [false, true] {
+    ///         let mut resources = resource_dir("./tests");
+    ///         resources
+    ///             .with_generated_filename(&generated_filename)
+    ///             .with_generated_fn("duplicate")
+    ///             .with_append(append);
+    ///         let result = resources.build();
+    ///         assert_eq!(result.is_ok(), !append);
+    ///     }
+    /// }
+    /// ```
+    pub fn with_append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    /// Inlines files smaller than `inline_threshold` bytes as literals (default is `0`, none).
+    ///
+    /// Inlined files don't need `include_bytes!`/`include_str!`, which reduces compile time
+    /// overhead of many tiny files. Larger files are included as usual.
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    ///
+    /// include!(concat!(env!("OUT_DIR"), "/generated_inline.rs"));
+    ///
+    /// fn main() {
+    ///     let resources = generate_inline();
+    ///     assert_eq!(resources["tiny.bin"].data, b"\0\"\\\xff");
+    ///     assert_eq!(resources["tiny.txt"].as_str(), Some("tiny \"text\"\n"));
+    ///     assert_eq!(resources["large.txt"].data, [b'a'; 64]);
+    ///
+    ///     let set = Path::new(env!("OUT_DIR")).join("generate_inline_sets/set_1.rs");
+    ///     let set = fs::read_to_string(set).unwrap();
+    ///     let insert = |key: &str| {
+    ///         set.lines()
+    ///             .find(|line| line.contains(&format!("insert({key:?},")))
+    ///             .unwrap()
+    ///             .to_owned()
+    ///     };
+    ///     assert!(!insert("tiny.bin").contains("i!("));
+    ///     assert!(!insert("tiny.txt").contains("s!("));
+    ///     assert!(insert("large.txt").contains("s!("));
+    /// }
+    /// ```
+    pub fn with_inline_threshold(&mut self, inline_threshold: u64) -> &mut Self {
+        self.inline_threshold = inline_threshold;
+        self
+    }
+
+    /// Sets the function returning `cfg` predicate of a resource by its key.
+    ///
+    /// Resources with a predicate are compiled in only if it holds, e.g. to embed
+    /// a helper binary of the target platform only:
+    ///
+    /// ```rust
+    /// fn platform(key: &str) -> Option<String> {
+    ///     let os = key.strip_prefix("helpers/")?.split('/').next()?;
+    ///     Some(format!("target_os = {os:?}"))
+    /// }
+    ///
+    /// assert_eq!(platform("helpers/linux/helper"), Some("target_os = \"linux\"".into()));
+    /// assert_eq!(platform("index.html"), None);
+    /// ```
+    ///
+    /// Generated function compiles even if the predicates exclude all resources.
+    ///
+    /// ```rust
+    /// include!(concat!(env!("OUT_DIR"), "/generated_cfg.rs"));
+    ///
+    /// fn main() {
+    ///     let resources = generate_cfg();
+    ///     assert_eq!(resources.len(), 3);
+    ///     assert!(!resources.contains_key("file1.txt"));
+    /// }
+    /// ```
+    pub fn with_cfg(&mut self, cfg: fn(key: &str) -> Option<String>) -> &mut Self {
+        self.cfg = Some(cfg);
+        self
+    }
+
+    /// Sets the function detecting immutable resources by their file names.
+    ///
+    /// Result is stored in [`Resource::immutable`](crate::Resource::immutable), use it
+    /// to cache content-hashed assets forever (default is none, no resource is immutable).
+    /// [`is_hashed_file_name`](crate::resource::is_hashed_file_name) detects file names
+    /// hashed by bundlers.
+    ///
+    /// ```rust
+    /// include!(concat!(env!("OUT_DIR"), "/generated_immutable.rs"));
+    ///
+    /// fn main() {
+    ///     let resources = generate_immutable();
+    ///     assert!(resources["app.abc123.js"].immutable);
+    ///     assert!(!resources["index.html"].immutable);
+    /// }
+    /// ```
+    ///
+    /// If the names of your bundler don't match it, pass a function with your own rule,
+    /// like a hex hash of fixed length in `app-[hash:8].js`:
+    ///
+    /// ```rust
+    /// use static_files::resource_dir;
+    ///
+    /// let mut resources = resource_dir("./dist");
+    /// resources.with_immutable(|file_name| {
+    ///     file_name.split(['-', '.']).nth(1).map_or(false, |hash| {
+    ///         hash.len() == 8 && hash.chars().all(|c| c.is_ascii_hexdigit())
+    ///     })
+    /// });
+    /// ```
+    pub fn with_immutable(&mut self, immutable: fn(file_name: &str) -> bool) -> &mut Self {
+        self.immutable = Some(immutable);
+        self
+    }
+
+    /// Sets the builder computing attributes of resources in a single pass over each file.
+    ///
+    /// Attributes set by the builder override the ones computed by other options.
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    /// use static_files::{
+    ///     resource::{FileContext, ResourceAttrs, ResourceBuilder},
+    ///     resource_dir,
+    /// };
+    ///
+    /// struct Attachments {
+    ///     extension: &'static str,
+    /// }
+    ///
+    /// impl ResourceBuilder for Attachments {
+    ///     fn build(&self, context: &FileContext) -> ResourceAttrs {
+    ///         if !context.key.ends_with(self.extension) {
+    ///             return ResourceAttrs::default();
+    ///         }
+    ///         ResourceAttrs {
+    ///             mime_type: Some("application/x-info".into()),
+    ///             download_name: Some(format!("{}.{}", context.data.len(), self.extension)),
+    ///             immutable: true,
+    ///             cfg: Some("feature = \"info\"".into()),
+    ///             ..ResourceAttrs::default()
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let mut resources = resource_dir("./tests");
+    /// resources
+    ///     .with_generated_filename(Path::new(&out_dir).join("generated_builder.rs"))
+    ///     .with_generated_fn("builder")
+    ///     .with_resource_builder(Attachments { extension: "info" });
+    /// resources.build().unwrap();
+    ///
+    /// let set = fs::read_to_string(Path::new(&out_dir).join("builder_sets/set_1.rs")).unwrap();
+    /// let insert = |key| {
+    ///     let key = format!("{key:?}");
+    ///     set.lines().find(|line| line.contains(&key)).unwrap().to_owned()
+    /// };
+    ///
+    /// let info = insert("file3.info");
+    /// assert!(info.starts_with("#[cfg(feature = \"info\")] "));
+    /// assert!(info.contains("\"application/x-info\")"));
+    /// assert!(info.contains(".with_download_name(\"0.info\")"));
+    /// assert!(info.ends_with(".with_immutable());"));
+    /// assert!(!insert("file1.txt").contains("cfg"));
+    /// ```
+    pub fn with_resource_builder<B>(&mut self, builder: B) -> &mut Self
+    where
+        B: ResourceBuilder + 'static,
+    {
+        self.resource_builder = Some(Arc::new(builder));
+        self
+    }
+
+    /// Reports resources larger than `threshold` bytes to `warn` (default is no warnings).
+    ///
+    /// Large bodies are better served from disk or a CDN, as some reverse proxies buffer them.
+    /// Warnings are advisory, resources are embedded anyway. In `build.rs` print them as
+    /// `cargo:warning` instructions:
+    ///
+    /// ```rust
+    /// use std::{
+    ///     env,
+    ///     path::Path,
+    ///     sync::atomic::{AtomicUsize, Ordering},
+    /// };
+    /// use static_files::resource_dir;
+    ///
+    /// static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let mut resources = resource_dir("./tests");
+    /// resources
+    ///     .with_generated_filename(Path::new(&out_dir).join("generated_size_warning.rs"))
+    ///     .with_generated_fn("size_warning")
+    ///     .with_size_warning(100, |message| {
+    ///         println!("cargo:warning={message}");
+    ///         assert!(message.starts_with("resource \"index.html\" has 200 bytes"));
+    ///         WARNINGS.fetch_add(1, Ordering::SeqCst);
+    ///     });
+    /// resources.build().unwrap();
+    ///
+    /// assert_eq!(WARNINGS.load(Ordering::SeqCst), 1);
+    /// ```
+    pub fn with_size_warning(&mut self, threshold: u64, warn: fn(message: &str)) -> &mut Self {
+        self.size_warning = Some((threshold, warn));
+        self
+    }
+
+    /// Generates unit type `bundle_type` implementing [`ResourceBundle`](crate::resource::ResourceBundle)
+    /// with the generated function (default is none).
+    ///
+    /// Use it to select one of several generated functions at runtime, e.g. themes.
+    /// Requires the default resource namespace.
+    ///
+    /// ```rust
+    /// use static_files::resource::ResourceBundle;
+    ///
+    /// include!(concat!(env!("OUT_DIR"), "/generated_bundles.rs"));
+    ///
+    /// fn main() {
+    ///     let bundles: [(&str, &dyn ResourceBundle); 2] = [("light", &Light), ("dark", &Dark)];
+    ///     let select = |name| bundles.iter().find(|(bundle, _)| *bundle == name).unwrap().1;
+    ///
+    ///     assert!(select("light").load().contains_key("file1.txt"));
+    ///     assert!(select("dark").load().contains_key("utf8.txt"));
+    /// }
+    /// ```
+    pub fn with_bundle_type<S>(&mut self, bundle_type: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.bundle_type = Some(bundle_type.into());
+        self
+    }
+
+    /// Generates function taking a prefix prepended to resource keys (default is `false`).
+    ///
+    /// Use it to choose the mount path of resources at runtime instead of at build time.
+    /// Generated function returns `HashMap<String, Resource>`, as keys are built at runtime.
+    ///
+    /// ```rust
+    /// include!(concat!(env!("OUT_DIR"), "/generated_prefix.rs"));
+    ///
+    /// fn main() {
+    ///     let resources = generate_prefix("/static/");
+    ///     assert_eq!(resources.len(), 4);
+    ///     assert!(resources.contains_key("/static/file1.txt"));
+    ///
+    ///     let resources = generate_prefix("assets/");
+    ///     assert!(resources.contains_key("assets/index.html"));
+    ///     assert!(!resources.contains_key("index.html"));
+    /// }
+    /// ```
+    pub fn with_prefix_argument(&mut self, prefix_argument: bool) -> &mut Self {
+        self.prefix_argument = prefix_argument;
+        self
+    }
+
+    /// Sets `cfg` predicate of the generated module, like `feature = "assets"` (default is none).
+    ///
+    /// Resources are compiled in only if the predicate holds, otherwise the generated function
+    /// returns no resources, so libraries can ship optional embedded resources behind a feature
+    /// and callers compile either way. Constants, like [`ResourceDir::with_keys_const`],
+    /// describe the resources as if the predicate holds.
+    ///
+    /// ```rust
+    /// include!(concat!(env!("OUT_DIR"), "/generated_gated.rs"));
+    ///
+    /// fn main() {
+    ///     // Gated by `not(target_os = "none")`.
+    ///     assert_eq!(generate_gated().len(), 4);
+    ///     // Gated by `target_os = "none"`.
+    ///     assert!(generate_gated_off().is_empty());
+    /// }
+    /// ```
+    pub fn with_fn_cfg<S: Into<String>>(&mut self, predicate: S) -> &mut Self {
+        self.fn_cfg = Some(predicate.into());
+        self
+    }
+
+    /// Generates `KEYS` constant listing keys of resources sorted, next to the generated function
+    /// (default is `false`).
+    ///
+    /// Use it to check which resources are embedded without reading set modules.
+    /// Keys of resources excluded by `cfg` predicates are listed too. Can't be combined
+    /// with another function generated into the same file with the constant.
+    ///
+    /// ```rust
+    /// include!(concat!(env!("OUT_DIR"), "/generated_keys.rs"));
+    ///
+    /// fn main() {
+    ///     assert_eq!(KEYS, ["file1.txt", "file2.txt", "file3.info", "index.html"]);
+    ///
+    ///     let resources = generate_keys();
+    ///     assert!(KEYS.iter().all(|key| resources.contains_key(key)));
+    /// }
+    /// ```
+    pub fn with_keys_const(&mut self, keys_const: bool) -> &mut Self {
+        self.keys_const = keys_const;
+        self
+    }
+
+    /// Sets modification time of all resources in Unix seconds (default is the ones of files).
+    ///
+    /// Otherwise, if `SOURCE_DATE_EPOCH` environment variable is set, later modification times
+    /// are clamped to it, following reproducible builds convention. Either way, builds of the
+    /// same files generate identical code on any machine. Resources are regenerated when it changes
+    /// with [`ResourceDir::with_change_detection`] or [`NpmBuild::change_detection`](crate::NpmBuild::change_detection).
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    /// use static_files::resource_dir;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let generate = |name: &str, fixed_modified: Option<u64>| {
+    ///     let mut resources = resource_dir("./tests");
+    ///     resources
+    ///         .with_generated_filename(Path::new(&out_dir).join(format!("generated_{name}.rs")))
+    ///         .with_generated_fn(name);
+    ///     if let Some(fixed_modified) = fixed_modified {
+    ///         resources.with_fixed_modified(fixed_modified);
+    ///     }
+    ///     resources.build().unwrap();
+    ///     fs::read_to_string(Path::new(&out_dir).join(format!("{name}_sets/set_1.rs"))).unwrap()
+    /// };
+    ///
+    /// env::set_var("SOURCE_DATE_EPOCH", "1");
+    /// let clamped = generate("clamped", None);
+    /// assert_eq!(clamped.matches(",1,\"").count(), 4);
+    /// assert_eq!(generate("clamped", None), clamped);
+    ///
+    /// let fixed = generate("fixed", Some(86_400));
+    /// assert_eq!(fixed.matches(",86400,\"").count(), 4);
+    /// ```
+    pub fn with_fixed_modified(&mut self, modified: u64) -> &mut Self {
+        self.fixed_modified = Some(modified);
+        self
+    }
+
+    /// Generates change detection instructions for the resource directory and merged ones
+    /// (default is `false`).
+    ///
+    /// Cargo reruns the build script when their files or `SOURCE_DATE_EPOCH` environment variable
+    /// change. Without it, Cargo reruns the build script on changes in the package, so enable it
+    /// for resources outside of the package, or to rerun only on changes of resources.
+    ///
+    /// ```rust
+    /// use std::{env, path::Path};
+    /// use static_files::resource_dir;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let mut resources = resource_dir("./tests");
+    /// resources
+    ///     .with_generated_filename(Path::new(&out_dir).join("generated_change_detection.rs"))
+    ///     .with_change_detection(true);
+    /// resources.build().unwrap();
+    /// ```
+    #[cfg(feature = "change-detection")]
+    pub fn with_change_detection(&mut self, change_detection: bool) -> &mut Self {
+        self.change_detection = change_detection;
+        self
+    }
+
+    /// Reads modification times of resources from `manifest` (default is the ones of files).
+    ///
+    /// Use it if a bundler records authoritative timestamps, as checkouts reset file times.
+    /// Each line of the manifest is a resource key followed by whitespace and Unix time in seconds,
+    /// empty lines and lines starting with `#` are ignored. Unlisted resources keep file times.
+    ///
+    /// ```rust
+    /// include!(concat!(env!("OUT_DIR"), "/generated_modified.rs"));
+    ///
+    /// fn main() {
+    ///     let resources = generate_modified();
+    ///     assert_eq!(resources["file1.txt"].modified, 1_700_000_000);
+    ///     assert_ne!(resources["file2.txt"].modified, 1_700_000_000);
+    /// }
+    /// ```
+    pub fn with_modified_from_manifest<P: AsRef<Path>>(&mut self, manifest: P) -> &mut Self {
+        self.modified_manifest = Some(manifest.as_ref().into());
+        self
+    }
+
+    /// Sets the path of the module providing the resource type
+    /// (default is `::static_files::resource`).
+    ///
+    /// Use it to generate resources of your own type, e.g. to avoid depending
+    /// on `static-files` at runtime. The module must provide:
+    /// - `Resource` type
+    /// - `new_resource(&'static [u8], u64, &'static str) -> Resource` function
+    /// - `new_text_resource(&'static str, u64, &'static str) -> Resource` function
+    ///
+    /// Image dimensions and preload hints require `Resource::with_dimensions`
+    /// and `Resource::with_preload` methods and `PreloadHint` type as in [`crate::resource`].
+    ///
+    /// ```rust
+    /// include!(concat!(env!("OUT_DIR"), "/generated_custom.rs"));
+    ///
+    /// mod custom {
+    ///     pub struct Resource {
+    ///         pub data: &'static [u8],
+    ///         pub mime_type: &'static str,
+    ///     }
+    ///
+    ///     pub const fn new_resource(data: &'static [u8], _: u64, mime_type: &'static str) -> Resource {
+    ///         Resource { data, mime_type }
+    ///     }
+    ///
+    ///     pub const fn new_text_resource(text: &'static str, _: u64, mime_type: &'static str) -> Resource {
+    ///         new_resource(text.as_bytes(), 0, mime_type)
+    ///     }
+    /// }
+    ///
+    /// fn main() {
+    ///     let resources = generate_custom();
+    ///     assert_eq!(resources.len(), 4);
+    ///     assert_eq!(resources["index.html"].mime_type, "text/html");
+    /// }
+    /// ```
+    pub fn with_resource_namespace<S>(&mut self, resource_namespace: S) -> &mut Self
+    where
+        S: Into<String>,
+    {
+        self.resource_namespace = Some(resource_namespace.into());
+        self
+    }
+
+    /// Adds `use` declarations of `paths` to generated modules, like `crate::assets::Ext`
+    /// (default is none).
+    ///
+    /// Use it to bring into scope items needed by the resource constructors of a custom
+    /// [resource namespace](ResourceDir::with_resource_namespace), set modules import them too.
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    /// use static_files::resource_dir;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let out_dir = Path::new(&out_dir);
+    /// let mut resources = resource_dir("./tests");
+    /// resources
+    ///     .with_generated_filename(out_dir.join("generated_module_uses.rs"))
+    ///     .with_generated_fn("module_uses")
+    ///     .with_module_uses(["crate::assets::ResourceExt", "std::borrow::Cow"]);
+    /// resources.build().unwrap();
+    ///
+    /// let module = fs::read_to_string(out_dir.join("module_uses_sets/mod.rs")).unwrap();
+    /// assert!(module.contains("use crate::assets::ResourceExt;"));
+    /// assert!(module.contains("use std::borrow::Cow;"));
+    /// ```
+    pub fn with_module_uses<I, S>(&mut self, paths: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.module_uses = paths.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Writes set modules in parallel (default is `true`).
+    ///
+    /// Generated code is the same either way.
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    /// use static_files::resource_dir;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let out_dir = Path::new(&out_dir);
+    ///
+    /// for (generated_fn, parallel) in [("sequential", false), ("parallel", true)] {
+    ///     let mut resources = resource_dir("./tests");
+    ///     resources
+    ///         .with_generated_filename(out_dir.join(format!("generated_{generated_fn}.rs")))
+    ///         .with_generated_fn(generated_fn)
+    ///         .with_count_per_module(1)
+    ///         .with_parallel(parallel);
+    ///     resources.build().unwrap();
+    /// }
+    ///
+    /// let read_tree = |generated_fn: &str| {
+    ///     let module_dir = out_dir.join(format!("{generated_fn}_sets"));
+    ///     let mut files = fs::read_dir(&module_dir)
+    ///         .unwrap()
+    ///         .map(|entry| {
+    ///             let path = entry.unwrap().path();
+    ///             let content = fs::read_to_string(&path).unwrap();
+    ///             (path.file_name().unwrap().to_owned(), content.replace(generated_fn, "generated"))
+    ///         })
+    ///         .collect::<Vec<_>>();
+    ///     files.sort();
+    ///     files
+    /// };
+    ///
+    /// let parallel = read_tree("parallel");
+    /// assert_eq!(parallel.len(), 5);
+    /// assert_eq!(read_tree("sequential"), parallel);
+    /// ```
+    #[cfg(feature = "parallel")]
+    pub fn with_parallel(&mut self, parallel: bool) -> &mut Self {
+        self.sequential = !parallel;
+        self
+    }
+
+    /// Generates `MANIFEST_HASH` constant with SHA-256 of resource keys and data (default is `false`).
+    ///
+    /// Check it at runtime with [`verify_integrity`](crate::resource::verify_integrity).
+    /// Can't be combined with query aliases and `cfg` predicates, which change the set of
+    /// resources at runtime, nor with another function generated into the same file with the hash.
+    #[cfg(feature = "integrity")]
+    pub fn with_manifest_hash(&mut self, manifest_hash: bool) -> &mut Self {
+        self.manifest_hash = manifest_hash;
+        self
+    }
+
+    /// Generates `TREE_HASH` constant with SHA-256 of sorted SHA-256 of resource contents
+    /// (default is `false`).
+    ///
+    /// Unlike `MANIFEST_HASH` it doesn't depend on keys, so it only changes with contents
+    /// of collected files, which makes it usable as a cache key of the embedded assets.
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    /// use static_files::resource_dir;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let out_dir = Path::new(&out_dir);
+    /// let tree_dir = out_dir.join("tree");
+    /// let _ = fs::remove_dir_all(&tree_dir);
+    /// fs::create_dir_all(&tree_dir).unwrap();
+    /// fs::write(tree_dir.join("a.txt"), "a").unwrap();
+    /// fs::write(tree_dir.join("b.txt"), "b").unwrap();
+    ///
+    /// let generated = out_dir.join("generated_tree.rs");
+    /// let tree_hash = || {
+    ///     let mut resources = resource_dir(&tree_dir);
+    ///     resources
+    ///         .with_generated_filename(&generated)
+    ///         .with_generated_fn("tree")
+    ///         .with_tree_hash(true);
+    ///     resources.build().unwrap();
+    ///     let generated = fs::read_to_string(&generated).unwrap();
+    ///     let line = generated.lines().find(|line| line.contains("TREE_HASH")).unwrap();
+    ///     line.to_owned()
+    /// };
+    ///
+    /// let first = tree_hash();
+    /// assert_eq!(tree_hash(), first);
+    ///
+    /// fs::write(tree_dir.join("b.txt"), "changed").unwrap();
+    /// assert_ne!(tree_hash(), first);
+    /// ```
+    #[cfg(feature = "integrity")]
+    pub fn with_tree_hash(&mut self, tree_hash: bool) -> &mut Self {
+        self.tree_hash = tree_hash;
+        self
+    }
+
+    /// Writes JSON manifest of resources to `path` (default is none).
+    ///
+    /// It lists `url`, `size` in bytes, `mime` type, hex SHA-256 `hash` and `modified` time
+    /// in seconds since Unix epoch of each resource sorted by URL, for tools like CDN uploaders.
+    /// Entries are collected while resources are generated, so each file is read once.
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    ///
+    /// use sha2::Digest;
+    /// use static_files::resource_dir;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let out_dir = Path::new(&out_dir);
+    /// let manifest_path = out_dir.join("resources.json");
+    ///
+    /// let mut resources = resource_dir("./tests");
+    /// resources
+    ///     .with_generated_filename(out_dir.join("generated_json_manifest.rs"))
+    ///     .with_generated_fn("json_manifest")
+    ///     .with_fixed_modified(1_700_000_000)
+    ///     .with_manifest(&manifest_path);
+    /// resources.build().unwrap();
+    ///
+    /// let manifest: serde_json::Value =
+    ///     serde_json::from_slice(&fs::read(&manifest_path).unwrap()).unwrap();
+    /// let entries = manifest["resources"].as_array().unwrap();
+    /// let urls = entries.iter().map(|entry| entry["url"].as_str().unwrap()).collect::<Vec<_>>();
+    /// assert_eq!(urls, ["/file1.txt", "/file2.txt", "/file3.info", "/index.html"]);
+    ///
+    /// for entry in entries {
+    ///     let data = fs::read(Path::new("./tests").join(&entry["url"].as_str().unwrap()[1..])).unwrap();
+    ///     let hash = sha2::Sha256::digest(&data)
+    ///         .iter()
+    ///         .map(|byte| format!("{byte:02x}"))
+    ///         .collect::<String>();
+    ///     assert_eq!(entry["size"], data.len());
+    ///     assert_eq!(entry["hash"], hash);
+    ///     assert_eq!(entry["modified"], 1_700_000_000);
+    /// }
+    /// assert_eq!(entries[0]["mime"], "text/plain");
+    /// assert_eq!(entries[2]["mime"], "application/octet-stream");
+    /// assert_eq!(entries[3]["mime"], "text/html");
+    /// assert_eq!(entries[3]["size"], 200);
+    /// assert_eq!(
+    ///     entries[0]["hash"],
+    ///     "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    /// );
+    /// ```
+    #[cfg(feature = "manifest")]
+    pub fn with_manifest<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.json_manifest = Some(path.as_ref().into());
+        self
+    }
+
+    /// Embeds only resources changed since the release described by JSON manifest at `path`
+    /// (default is all resources), for updaters shipping only changed files.
+    ///
+    /// The manifest is the one written with [`ResourceDir::with_manifest`] for the previous release.
+    /// Resources with the same hex SHA-256 `hash` under the same key are skipped,
+    /// sorted keys of the manifest without a collected resource are generated
+    /// as `REMOVED_KEYS` constant. Manifest written with the delta lists only embedded resources.
+    ///
+    /// ```rust
+    /// use std::{env, fs, path::Path};
+    /// use static_files::resource_dir;
+    ///
+    /// let out_dir = env::var("OUT_DIR").unwrap();
+    /// let out_dir = Path::new(&out_dir);
+    /// let base_manifest = out_dir.join("delta_base.json");
+    /// let release = |version: &str, files: &[(&str, &str)]| {
+    ///     let web_dir = out_dir.join(format!("delta_{version}"));
+    ///     let _ = fs::remove_dir_all(&web_dir);
+    ///     fs::create_dir_all(&web_dir).unwrap();
+    ///     for (name, content) in files {
+    ///         fs::write(web_dir.join(name), content).unwrap();
+    ///     }
+    ///     let mut resources = resource_dir(&web_dir);
+    ///     resources
+    ///         .with_generated_filename(out_dir.join(format!("generated_delta_{version}.rs")))
+    ///         .with_generated_fn(format!("delta_{version}"));
+    ///     resources
+    /// };
+    ///
+    /// let mut v1 = release("v1", &[("app.js", "v1"), ("index.html", "v1"), ("old.css", "v1")]);
+    /// v1.with_manifest(&base_manifest);
+    /// v1.build().unwrap();
+    ///
+    /// let mut v2 = release("v2", &[("app.js", "v2"), ("index.html", "v1")]);
+    /// v2.with_base_manifest(&base_manifest);
+    /// v2.build().unwrap();
+    ///
+    /// let set = fs::read_to_string(out_dir.join("delta_v2_sets/set_1.rs")).unwrap();
+    /// assert!(set.contains("\"app.js\""));
+    /// assert!(!set.contains("\"index.html\""));
+    ///
+    /// let generated = fs::read_to_string(out_dir.join("generated_delta_v2.rs")).unwrap();
+    /// assert!(generated.contains("pub const REMOVED_KEYS: &[&str] = &[\"old.css\"];"));
+    /// ```
+    #[cfg(feature = "manifest")]
+    pub fn with_base_manifest<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.base_manifest = Some(path.as_ref().into());
+        self
+    }
+
+    /// Computes hex SHA-256 of resources as [`Resource::etag`](crate::Resource::etag) (default is `false`).
+    ///
+    /// ```rust
+    /// include!(concat!(env!("OUT_DIR"), "/generated_integrity.rs"));
+    ///
+    /// fn main() {
+    ///     use sha2::{Digest, Sha256};
+    ///
+    ///     let resources = generate_integrity();
+    ///     assert_eq!(
+    ///         resources["file2.txt"].etag,
+    ///         Some("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+    ///     );
+    ///
+    ///     let digest = Sha256::digest(std::fs::read("./tests/index.html").unwrap());
+    ///     let hex = digest.iter().map(|byte| format!("{byte:02x}")).collect::<String>();
+    ///     assert_eq!(resources["index.html"].etag, Some(hex.as_str()));
+    /// }
+    /// ```
+    #[cfg(feature = "integrity")]
+    pub fn with_etags(&mut self, etags: bool) -> &mut Self {
+        self.etags = etags;
+        self
+    }
+
+    /// Computes Subresource Integrity metadata of resources with SHA-384 digest
+    /// as [`Resource::integrity`](crate::Resource::integrity) (default is `false`).
+    ///
+    /// ```rust
+    /// include!(concat!(env!("OUT_DIR"), "/generated_integrity.rs"));
+    ///
+    /// fn main() {
+    ///     let resources = generate_integrity();
+    ///     assert_eq!(
+    ///         resources["file1.txt"].integrity,
+    ///         Some("sha384-OLBgp1GsljhM2TJ+sbHjaiH9txEUvgdDTAzHv2P24donTt6/529l+9Ua0vFImLlb")
+    ///     );
+    ///     assert_eq!(
+    ///         resources["index.html"].integrity,
+    ///         Some("sha384-9HLCJniUewXJepYM2A04MXEk1SKmJXm7FQjBn990HqGGm0t5J8TxUvZin8R1t4nu")
+    ///     );
+    /// }
+    /// ```
+    #[cfg(feature = "integrity")]
+    pub fn with_sri(&mut self, sri: bool) -> &mut Self {
+        self.sri = sri;
+        self
+    }
+
+    /// Generates function `router_fn` inserting resources of the generated function
+    /// into a [`matchit`](https://crates.io/crates/matchit) router (default is none).
+    ///
+    /// Use it to match static resources and dynamic routes with one router,
+    /// see [`insert_routes`](crate::resource::insert_routes). Requires the default resource namespace.
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "matchit")]
+    /// include!(concat!(env!("OUT_DIR"), "/generated_router.rs"));
+    ///
+    /// fn main() {
+    /// #   #[cfg(feature = "matchit")]
+    /// #   {
+    ///     use static_files::matchit::Router;
+    ///
+    ///     let mut router = Router::new();
+    ///     routes(&mut router).unwrap();
+    ///
+    ///     let matched = router.at("/file1.txt").unwrap();
+    ///     assert_eq!(matched.value.mime_type, "text/plain");
+    ///     assert!(router.at("/missing.txt").is_err());
+    /// #   }
+    /// }
+    /// ```
+    #[cfg(feature = "matchit")]
+    pub fn with_router_fn<S: Into<String>>(&mut self, router_fn: S) -> &mut Self {
+        self.router_fn = Some(router_fn.into());
+        self
+    }
+
+    /// Captures extended attributes `names` of resource files (default is none).
+    ///
+    /// Attributes are stored in [`Resource::xattrs`](crate::Resource::xattrs),
+    /// use [`Resource::restore_xattrs`](crate::Resource::restore_xattrs) to set them on extracted files.
+    /// Missing attributes are skipped, nothing is captured on platforms without extended attributes.
+    #[cfg(feature = "xattr")]
+    pub fn with_xattrs<I, S>(&mut self, names: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.xattrs = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Normalizes resource keys to Unicode normalization `form` (default is as in file names).
+    ///
+    /// File names may be in different forms on different systems, e.g. decomposed on macOS,
+    /// so normalize keys to look up resources by the same names everywhere.
+    /// The build fails if files in different forms have the same normalized key.
+    ///
+    /// ```rust
+    /// include!(concat!(env!("OUT_DIR"), "/generated_unicode.rs"));
+    ///
+    /// fn main() {
+    ///     let resources = generate_unicode();
+    ///     assert!(resources.contains_key("caf\u{e9}.txt"));
+    ///     assert!(!resources.contains_key("cafe\u{301}.txt"));
+    /// }
+    /// ```
+    #[cfg(feature = "unicode")]
+    pub fn with_unicode_normalization(&mut self, form: NormalizationForm) -> &mut Self {
+        self.unicode_normalization = Some(form);
+        self
+    }
+}
+
+/// Reads `key seconds` lines of modification times manifest.
+fn read_modified_manifest(manifest: &Path) -> io::Result<HashMap<String, u64>> {
+    let content = fs::read_to_string(manifest)?;
+    let mut modified = HashMap::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let entry = line
+            .rsplit_once(char::is_whitespace)
+            .and_then(|(key, seconds)| Some((key.trim_end(), seconds.parse().ok()?)));
+        if let Some((key, seconds)) = entry {
+            modified.insert(key.to_owned(), seconds);
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{}:{}: expected resource key and Unix time",
+                    manifest.display(),
+                    index + 1
+                ),
+            ));
+        }
+    }
+
+    Ok(modified)
+}
+
+fn rustfmt(generated_filename: &Path, module_dir: &Path) {
+    let mut files = vec![generated_filename.to_path_buf()];
+    if let Ok(entries) = fs::read_dir(module_dir) {
+        files.extend(
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().map_or(false, |ext| ext == "rs")),
+        );
+    }
+
+    let _ = Command::new("rustfmt")
+        .arg("--edition")
+        .arg("2021")
+        .args(files)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
 }
@@ -0,0 +1,140 @@
+/*!
+Resources from a git revision.
+*/
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use filetime::FileTime;
+use git2::{ObjectType, Repository, TreeWalkMode, TreeWalkResult};
+
+use super::resource_dir::{resource_dir, ResourceDir};
+use super::sets::fnv1a_hash;
+
+/// Generate resources for `subdir` of the git repository `repo_path` as of revision `rev`.
+///
+/// Files are read from the git object store, so uncommitted changes in the
+/// working tree do not affect the output. They are extracted to
+/// `OUT_DIR/resource_git/<commit id>-<hash of subdir>` and their modification time
+/// is the commit time. Resource keys are relative to `subdir`.
+///
+/// ```rust
+/// use std::{env, fs, path::Path};
+/// use static_files::resource_git;
+///
+/// let out_dir = env::var("OUT_DIR").unwrap();
+/// let repo_dir = Path::new(&out_dir).join("resource_git_repo");
+/// let _ = fs::remove_dir_all(&repo_dir);
+///
+/// let repo = git2::Repository::init(&repo_dir).unwrap();
+/// fs::create_dir_all(repo_dir.join("web")).unwrap();
+/// fs::write(repo_dir.join("web/index.html"), "committed").unwrap();
+/// fs::create_dir_all(repo_dir.join("docs")).unwrap();
+/// fs::write(repo_dir.join("docs/readme.txt"), "docs").unwrap();
+/// let mut index = repo.index().unwrap();
+/// index.add_path(Path::new("web/index.html")).unwrap();
+/// index.add_path(Path::new("docs/readme.txt")).unwrap();
+/// let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+/// let signature = git2::Signature::now("static-files", "static-files@localhost").unwrap();
+/// let commit = repo
+///     .commit(Some("HEAD"), &signature, &signature, "assets", &tree, &[])
+///     .unwrap();
+///
+/// fs::write(repo_dir.join("web/index.html"), "dirty").unwrap();
+/// fs::write(repo_dir.join("web/untracked.txt"), "untracked").unwrap();
+///
+/// let mut resources = resource_git(&repo_dir, "HEAD", "web").unwrap();
+/// // Other subdirectories of the same commit are extracted to their own directories.
+/// let _docs = resource_git(&repo_dir, "HEAD", "docs").unwrap();
+/// resources.with_generated_filename(repo_dir.join("generated.rs"));
+/// resources.build().unwrap();
+///
+/// let snapshot_dirs = fs::read_dir(Path::new(&out_dir).join("resource_git"))
+///     .unwrap()
+///     .map(|entry| entry.unwrap().path())
+///     .filter(|path| path.file_name().unwrap().to_str().unwrap().starts_with(&commit.to_string()))
+///     .collect::<Vec<_>>();
+/// assert_eq!(snapshot_dirs.len(), 2);
+/// assert!(snapshot_dirs
+///     .iter()
+///     .any(|dir| fs::read_to_string(dir.join("index.html")).map_or(false, |s| s == "committed")));
+///
+/// let set = fs::read_to_string(repo_dir.join("generate_sets/set_1.rs")).unwrap();
+/// assert!(set.contains("\"index.html\""));
+/// assert!(!set.contains("untracked.txt"));
+/// assert!(!set.contains("readme.txt"));
+/// ```
+///
+/// # Panics
+/// Panics if `OUT_DIR` environment variable is not set.
+pub fn resource_git<P: AsRef<Path>, S: AsRef<Path>>(
+    repo_path: P,
+    rev: &str,
+    subdir: S,
+) -> io::Result<ResourceDir> {
+    let repo = Repository::open(repo_path).map_err(git_error)?;
+    let commit = repo
+        .revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(git_error)?;
+    let modified = FileTime::from_unix_time(commit.time().seconds(), 0);
+
+    let mut tree = commit.tree().map_err(git_error)?;
+    if subdir.as_ref() != Path::new("") {
+        tree = tree
+            .get_path(subdir.as_ref())
+            .and_then(|entry| entry.to_object(&repo))
+            .and_then(|object| object.peel_to_tree())
+            .map_err(git_error)?;
+    }
+
+    let subdir_hash = fnv1a_hash(subdir.as_ref().to_string_lossy().bytes());
+    let snapshot_dir = Path::new(&env::var("OUT_DIR").unwrap())
+        .join("resource_git")
+        .join(format!("{}-{subdir_hash:016x}", commit.id()));
+    if snapshot_dir.exists() {
+        fs::remove_dir_all(&snapshot_dir)?;
+    }
+    fs::create_dir_all(&snapshot_dir)?;
+
+    let mut result = Ok(());
+    let walk = tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+        if entry.kind() != Some(ObjectType::Blob) || entry.filemode() == 0o120_000 {
+            return TreeWalkResult::Ok;
+        }
+
+        let name = match entry.name() {
+            Some(name) => name,
+            None => return TreeWalkResult::Ok,
+        };
+
+        result = repo
+            .find_blob(entry.id())
+            .map_err(git_error)
+            .and_then(|blob| {
+                let path = snapshot_dir.join(root).join(name);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::File::create(&path)?.write_all(blob.content())?;
+                filetime::set_file_mtime(path, modified)
+            });
+
+        if result.is_ok() {
+            TreeWalkResult::Ok
+        } else {
+            TreeWalkResult::Abort
+        }
+    });
+    result?;
+    walk.map_err(git_error)?;
+
+    Ok(resource_dir(snapshot_dir))
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn git_error(err: git2::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err.message())
+}
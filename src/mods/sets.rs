@@ -2,15 +2,25 @@
 Support for module based generations. Use it for large data sets (more than 128 Mb).
  */
 use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
     fs::{self, File, Metadata},
     io::{self, Write},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 use super::resource::{
     collect_resources, generate_function_end, generate_function_header, generate_resource_insert,
-    generate_uses, generate_variable_header, generate_variable_return, DEFAULT_VARIABLE_NAME,
+    generate_uses, generate_variable_header, generate_variable_return, glob_match,
+    resource_mime_type, EntryAction, FileContext, InsertOptions, KeyHasher,
+    DEFAULT_RESOURCE_NAMESPACE, DEFAULT_VARIABLE_NAME, UNSUPPORTED_GLOB_CHARS,
 };
+#[cfg(feature = "integrity")]
+use super::resource::{manifest_hash, tree_hash};
+#[cfg(feature = "manifest")]
+use super::resource::{read_json_manifest_hashes, sha256_hex, write_json_manifest};
+use super::resource_dir::Filter;
 
 /// Defines the split strategie.
 pub trait SetSplitStrategie {
@@ -20,6 +30,15 @@ pub trait SetSplitStrategie {
     fn should_split(&self) -> bool;
     /// Resets internal counters after split.
     fn reset(&mut self);
+    /// Returns the number of modules files are assigned to by [`SetSplitStrategie::module_index`],
+    /// instead of splitting modules sequentially (default is `None`).
+    fn module_count(&self) -> Option<usize> {
+        None
+    }
+    /// Returns the index of the module of the file with `key`, used with [`SetSplitStrategie::module_count`].
+    fn module_index(&self, _key: &str) -> usize {
+        0
+    }
 }
 
 /// Split modules by files count.
@@ -49,6 +68,241 @@ impl SetSplitStrategie for SplitByCount {
     }
 }
 
+/// Split modules by total size of files in bytes.
+///
+/// A module is closed once its files reach the size, so it holds at least one file,
+/// and exceeds the size by less than the size of its last file.
+pub struct SplitBySize {
+    current: u64,
+    max: u64,
+}
+
+impl SplitBySize {
+    #[must_use]
+    pub fn new(max_bytes: u64) -> Self {
+        Self {
+            current: 0,
+            max: max_bytes,
+        }
+    }
+}
+
+impl SetSplitStrategie for SplitBySize {
+    fn register(&mut self, _path: &Path, metadata: &Metadata) {
+        self.current += metadata.len();
+    }
+
+    fn should_split(&self) -> bool {
+        self.current >= self.max
+    }
+
+    fn reset(&mut self) {
+        self.current = 0;
+    }
+}
+
+/// Split modules by files count or by total size of files in bytes, whichever is reached first.
+///
+/// Many small files and a few large files slow down compilation differently,
+/// so modules are capped by both.
+///
+/// ```rust
+/// use std::{env, fs, path::Path};
+/// use static_files::sets::{SetSplitStrategie, SplitByCountOrSize};
+///
+/// let dir = Path::new(&env::var("OUT_DIR").unwrap()).join("count_or_size");
+/// fs::create_dir_all(&dir).unwrap();
+///
+/// let mut strategie = SplitByCountOrSize::new(3, 100);
+/// let mut modules = vec![vec![]];
+/// for (index, size) in [10, 10, 10, 100, 10].into_iter().enumerate() {
+///     let path = dir.join(format!("{index}.bin"));
+///     fs::write(&path, vec![0; size]).unwrap();
+///
+///     if strategie.should_split() {
+///         strategie.reset();
+///         modules.push(vec![]);
+///     }
+///     strategie.register(&path, &fs::metadata(&path).unwrap());
+///     modules.last_mut().unwrap().push(index);
+/// }
+///
+/// assert_eq!(modules, [vec![0, 1, 2], vec![3], vec![4]]);
+/// ```
+pub struct SplitByCountOrSize {
+    count: SplitByCount,
+    size: SplitBySize,
+}
+
+impl SplitByCountOrSize {
+    #[must_use]
+    pub fn new(max_count: usize, max_bytes: u64) -> Self {
+        Self {
+            count: SplitByCount::new(max_count),
+            size: SplitBySize::new(max_bytes),
+        }
+    }
+}
+
+impl SetSplitStrategie for SplitByCountOrSize {
+    fn register(&mut self, path: &Path, metadata: &Metadata) {
+        self.count.register(path, metadata);
+        self.size.register(path, metadata);
+    }
+
+    fn should_split(&self) -> bool {
+        self.count.should_split() || self.size.should_split()
+    }
+
+    fn reset(&mut self) {
+        self.count.reset();
+        self.size.reset();
+    }
+}
+
+/// Split files into a fixed number of modules by hash of their top-level directory.
+///
+/// Files under the same top-level directory, or top-level files with the same name, always
+/// go to the same module, so changes of other directories don't change the module.
+///
+/// Compared to one module per directory, the number of modules is fixed whatever the number
+/// of directories, but modules are only as balanced as the hashes of directories:
+/// one large directory makes one large module. Some modules are empty if there are
+/// fewer directories than modules, so combine it with [`SetModuleNaming::Prefix`] naming.
+pub struct SplitByTopDirHash {
+    modules: usize,
+}
+
+impl SplitByTopDirHash {
+    /// Creates a strategie with `modules` modules, at least one.
+    #[must_use]
+    pub fn new(modules: usize) -> Self {
+        Self {
+            modules: modules.max(1),
+        }
+    }
+}
+
+impl SetSplitStrategie for SplitByTopDirHash {
+    fn register(&mut self, _path: &Path, _metadata: &Metadata) {}
+
+    fn should_split(&self) -> bool {
+        false
+    }
+
+    fn reset(&mut self) {}
+
+    fn module_count(&self) -> Option<usize> {
+        Some(self.modules)
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn module_index(&self, key: &str) -> usize {
+        let top_dir = key.split('/').next().unwrap_or(key);
+        (fnv1a_hash(top_dir.bytes()) % self.modules as u64) as usize
+    }
+}
+
+/// Defines which resource is embedded when several ones have the same key,
+/// like files of merged directories.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyConflict {
+    /// Generation fails with an error naming paths of both resources.
+    Error,
+    /// The resource collected first is embedded, resources of merged directories come last.
+    FirstWins,
+    /// The resource collected last is embedded, resources of merged directories come last.
+    LastWins,
+}
+
+impl Default for KeyConflict {
+    fn default() -> Self {
+        Self::Error
+    }
+}
+
+/// Default prefix of the generated set module names.
+pub const DEFAULT_SET_MODULE_PREFIX: &str = "set_";
+
+/// Defines how set modules are named.
+///
+/// Every generated name must be a valid Rust identifier and unique within the module.
+#[derive(Clone)]
+pub enum SetModuleNaming {
+    /// Module name is the prefix followed by the module index, like `set_1`.
+    Prefix(String),
+    /// Module name is produced from the module index by the function.
+    Func(fn(usize) -> String),
+    /// Module name is the prefix followed by a hash of resource keys of the module, like `set_1a2b3c4d5e6f`.
+    ///
+    /// Names don't depend on other modules, so adding or removing resources renames only
+    /// modules with changed membership, which keeps incremental compilation caches valid.
+    ContentHash(String),
+}
+
+impl Default for SetModuleNaming {
+    fn default() -> Self {
+        Self::Prefix(DEFAULT_SET_MODULE_PREFIX.into())
+    }
+}
+
+impl SetModuleNaming {
+    /// Returns the name of the set module with `module_index` (starting from 1).
+    ///
+    /// [`SetModuleNaming::ContentHash`] names depend on resource keys, which are unknown here,
+    /// so the name of an empty module is returned.
+    pub fn module_name(&self, module_index: usize) -> io::Result<String> {
+        self.module_name_with_keys(module_index, &[])
+    }
+
+    pub(crate) fn module_name_with_keys(
+        &self,
+        module_index: usize,
+        keys: &[String],
+    ) -> io::Result<String> {
+        let module_name = match self {
+            Self::Prefix(prefix) => format!("{prefix}{module_index}"),
+            Self::Func(func) => func(module_index),
+            Self::ContentHash(prefix) => format!("{prefix}{:012x}", keys_hash(keys) >> 16),
+        };
+
+        if is_identifier(&module_name) {
+            Ok(module_name)
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("set module name {module_name:?} is not a valid identifier"),
+            ))
+        }
+    }
+}
+
+/// Returns FNV-1a hash of `keys`, stable across platforms and compiler versions.
+fn keys_hash(keys: &[String]) -> u64 {
+    fnv1a_hash(keys.iter().flat_map(|key| key.bytes().chain(Some(0))))
+}
+
+/// Returns FNV-1a hash of `bytes`, stable across Rust versions and platforms.
+pub(crate) fn fnv1a_hash(bytes: impl Iterator<Item = u8>) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            name != "_" && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
 /// Generate resources for `project_dir` using `filter`
 /// breaking them into separate modules using `set_split_strategy` (recommended for large > 128 Mb setups).
 ///
@@ -102,86 +356,1088 @@ where
     G: AsRef<Path>,
     S: SetSplitStrategie,
 {
-    let resources = collect_resources(&project_dir, filter)?;
+    generate_resources_sets_with_filter(
+        project_dir,
+        |path| filter.map_or(true, |filter| filter(path)),
+        generated_filename,
+        module_name,
+        fn_name,
+        set_split_strategy,
+    )
+}
 
-    let mut generated_file = File::create(&generated_filename)?;
+/// Same as [`generate_resources_sets`], but `filter` can be a closure capturing its environment.
+///
+/// ```rust
+/// use std::{env, fs, path::Path};
+/// use static_files::sets::{generate_resources_sets_with_filter, SplitByCount};
+///
+/// let skipped = Path::new("./tests/file1.txt").canonicalize().unwrap();
+/// let generated_filename = env::temp_dir().join("static_files_sets_with_filter.rs");
+/// generate_resources_sets_with_filter(
+///     "./tests",
+///     |path| path.canonicalize().map_or(true, |path| path != skipped),
+///     &generated_filename,
+///     "sets_with_filter",
+///     "generate",
+///     &mut SplitByCount::new(2),
+/// )
+/// .unwrap();
+///
+/// let sets_dir = env::temp_dir().join("sets_with_filter");
+/// let generated = fs::read_dir(sets_dir)
+///     .unwrap()
+///     .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+///     .collect::<String>();
+/// assert!(!generated.contains("file1.txt"));
+/// assert!(generated.contains("file2.txt"));
+/// ```
+pub fn generate_resources_sets_with_filter<P, F, G, S>(
+    project_dir: P,
+    filter: F,
+    generated_filename: G,
+    module_name: &str,
+    fn_name: &str,
+    set_split_strategy: &mut S,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    F: Fn(&Path) -> bool,
+    G: AsRef<Path>,
+    S: SetSplitStrategie,
+{
+    generate_resources_sets_with_options(
+        project_dir,
+        Some(filter),
+        generated_filename,
+        module_name,
+        fn_name,
+        set_split_strategy,
+        &SetsOptions::default(),
+    )
+}
 
-    let module_dir = generated_filename.as_ref().parent().map_or_else(
-        || PathBuf::from(module_name),
-        |parent| parent.join(module_name),
+/// Additional options of sets generation.
+#[derive(Clone, Default)]
+#[allow(clippy::struct_excessive_bools)]
+pub(crate) struct SetsOptions {
+    pub(crate) set_module_naming: SetModuleNaming,
+    /// Directories with resources merged into the collected ones, with their filters.
+    pub(crate) merged_dirs: Vec<(PathBuf, Option<Filter>)>,
+    pub(crate) relative_includes: bool,
+    pub(crate) key_hasher: KeyHasher,
+    pub(crate) insert: InsertOptions,
+    /// Appends to the generated file instead of overwriting it.
+    pub(crate) append: bool,
+    /// Writes set modules one by one instead of in parallel.
+    #[cfg(feature = "parallel")]
+    pub(crate) sequential: bool,
+    pub(crate) size_warning: Option<SizeWarning>,
+    /// Globs of collected keys, all keys are collected if empty.
+    pub(crate) include_globs: Vec<String>,
+    /// Decides whether collected files are embedded and under which keys.
+    pub(crate) entry_action: Option<EntryActionFn>,
+    /// Globs of keys not collected.
+    pub(crate) exclude_globs: Vec<String>,
+    /// Keys of resources which must be collected.
+    pub(crate) required_keys: Vec<String>,
+    /// Fails if several resources have the same content.
+    pub(crate) forbid_duplicates: bool,
+    /// Resolves resources with the same key.
+    pub(crate) key_conflict: KeyConflict,
+    /// Name of the generated type implementing `ResourceBundle` with the generated function.
+    pub(crate) bundle_type: Option<String>,
+    /// Generates `KEYS` constant listing keys of resources.
+    pub(crate) keys_const: bool,
+    /// Generates function taking a prefix prepended to keys at runtime.
+    pub(crate) prefix_argument: bool,
+    /// Count of resources below which they are generated in a module inlined into the generated file.
+    pub(crate) flat_threshold: usize,
+    /// `cfg` predicate of the module, the function returns no resources if it doesn't hold.
+    pub(crate) fn_cfg: Option<String>,
+    /// Paths imported by `use` declarations in generated modules.
+    pub(crate) module_uses: Vec<String>,
+    /// Generates `MANIFEST_HASH` constant of resources.
+    #[cfg(feature = "integrity")]
+    pub(crate) manifest_hash: bool,
+    /// Generates `TREE_HASH` constant of resource contents.
+    #[cfg(feature = "integrity")]
+    pub(crate) tree_hash: bool,
+    /// Path of the JSON manifest of resources.
+    #[cfg(feature = "manifest")]
+    pub(crate) json_manifest: Option<PathBuf>,
+    /// Path of the JSON manifest of the previous release, resources with unchanged hashes are skipped.
+    #[cfg(feature = "manifest")]
+    pub(crate) base_manifest: Option<PathBuf>,
+    /// Name of the generated function inserting resources into a `matchit` router.
+    #[cfg(feature = "matchit")]
+    pub(crate) router_fn: Option<String>,
+}
+
+/// Returns the decision about the collected file.
+pub(crate) type EntryActionFn = Arc<dyn Fn(&FileContext) -> EntryAction + Send + Sync>;
+
+/// Size in bytes above which resources are reported to the function as too large to embed.
+pub(crate) type SizeWarning = (u64, fn(&str));
+
+pub(crate) fn generate_resources_sets_with_options<P, F, G, S>(
+    project_dir: P,
+    filter: Option<F>,
+    generated_filename: G,
+    module_name: &str,
+    fn_name: &str,
+    set_split_strategy: &mut S,
+    options: &SetsOptions,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    F: Fn(&Path) -> bool,
+    G: AsRef<Path>,
+    S: SetSplitStrategie + ?Sized,
+{
+    check_options(options)?;
+
+    let mut resources = collect_resources(&project_dir, filter)?;
+    exclude_generated(
+        &mut resources,
+        project_dir.as_ref(),
+        generated_filename.as_ref(),
+        module_name,
     );
-    fs::create_dir_all(&module_dir)?;
+    let options = collect_merged_dirs(&mut resources, options)?;
+    let options = apply_entry_action(&mut resources, project_dir.as_ref(), &options)?;
+    let options = &*options;
+    retain_globs(&mut resources, project_dir.as_ref(), options);
+    resolve_key_conflicts(&mut resources, project_dir.as_ref(), options)?;
+    check_required_keys(&resources, project_dir.as_ref(), options)?;
+    if options.forbid_duplicates {
+        check_duplicates(&resources, project_dir.as_ref(), &options.insert)?;
+    }
+    warn_large_resources(&resources, project_dir.as_ref(), options);
+    #[cfg(feature = "manifest")]
+    let removed_keys = skip_unchanged(&mut resources, project_dir.as_ref(), options)?;
+    if options.insert.order {
+        resources.sort_by_cached_key(|(path, _)| options.insert.resource_key(&project_dir, path));
+    }
+
+    let mut generated_file = if options.append {
+        open_generated_file_to_append(generated_filename.as_ref(), module_name, fn_name)?
+    } else {
+        File::create(&generated_filename)?
+    };
+
+    let project_dir = project_dir.as_ref();
+    if resources.len() < options.flat_threshold {
+        let mut module = vec![];
+        write_flat_module(
+            &mut module,
+            &resources,
+            generated_filename.as_ref(),
+            project_dir,
+            fn_name,
+            options,
+        )?;
+        write_module_use(
+            &mut generated_file,
+            module_name,
+            Some(&module),
+            fn_name,
+            options,
+        )?;
+    } else {
+        write_set_modules(
+            &resources,
+            &module_dir(generated_filename.as_ref(), module_name),
+            project_dir,
+            set_split_strategy,
+            fn_name,
+            options,
+        )?;
+        write_module_use(&mut generated_file, module_name, None, fn_name, options)?;
+    }
+
+    write_constants(&mut generated_file, &resources, project_dir, options)?;
+    #[cfg(feature = "manifest")]
+    if let Some(removed_keys) = removed_keys {
+        writeln!(
+            generated_file,
+            "pub const REMOVED_KEYS: &[&str] = &{removed_keys:?};"
+        )?;
+    }
+    #[cfg(feature = "manifest")]
+    if let (Some(json_manifest), Some(manifest)) =
+        (&options.json_manifest, &options.insert.manifest)
+    {
+        write_json_manifest(json_manifest, manifest)?;
+    }
 
+    write_wrappers(&mut generated_file, fn_name, options)
+}
+
+/// Splits `resources` into set modules written to `module_dir`, with `mod.rs` merging them.
+fn write_set_modules<S: SetSplitStrategie + ?Sized>(
+    resources: &[(PathBuf, Metadata)],
+    module_dir: &Path,
+    project_dir: &Path,
+    set_split_strategy: &mut S,
+    fn_name: &str,
+    options: &SetsOptions,
+) -> io::Result<()> {
+    fs::create_dir_all(module_dir)?;
+    let include_base = if options.relative_includes {
+        Some(module_dir.canonicalize()?)
+    } else {
+        None
+    };
+
+    let sets = split_resources(
+        resources,
+        set_split_strategy,
+        &options.set_module_naming,
+        project_dir,
+        &options.insert,
+    )?;
+
+    // Order of the first resource of each set.
+    let first_orders = sets
+        .iter()
+        .scan(0, |order, (_, set_resources)| {
+            let first_order = *order;
+            *order += set_resources.len();
+            Some(first_order)
+        })
+        .collect::<Vec<_>>();
+
+    let write_set = |((set_module_name, set_resources), first_order): (&ResourceSet, &usize)| {
+        write_set_module(
+            module_dir,
+            set_module_name,
+            set_resources,
+            *first_order,
+            project_dir,
+            include_base.as_deref(),
+            options,
+        )
+    };
+
+    #[cfg(feature = "parallel")]
+    if options.sequential {
+        sets.iter().zip(&first_orders).try_for_each(write_set)?;
+    } else {
+        use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+
+        sets.par_iter().zip(&first_orders).try_for_each(write_set)?;
+    }
+    #[cfg(not(feature = "parallel"))]
+    sets.iter().zip(&first_orders).try_for_each(write_set)?;
+
+    let set_module_names = sets
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>();
     let mut module_file = File::create(module_dir.join("mod.rs"))?;
+    write_module(
+        &mut module_file,
+        &set_module_names,
+        fn_name,
+        options,
+        |module_file| {
+            for set_module_name in &set_module_names {
+                writeln!(
+                    module_file,
+                    "{set_module_name}::generate(&mut {DEFAULT_VARIABLE_NAME});",
+                )?;
+            }
+            Ok(())
+        },
+    )
+}
+
+/// Writes module with function `fn_name` inserting `resources` itself, to be inlined
+/// into `generated_filename`.
+fn write_flat_module<W: Write>(
+    module: &mut W,
+    resources: &[(PathBuf, Metadata)],
+    generated_filename: &Path,
+    project_dir: &Path,
+    fn_name: &str,
+    options: &SetsOptions,
+) -> io::Result<()> {
+    let include_base = if options.relative_includes {
+        let generated_dir = match generated_filename.parent() {
+            Some(parent) if parent != Path::new("") => parent,
+            _ => Path::new("."),
+        };
+        Some(generated_dir.canonicalize()?)
+    } else {
+        None
+    };
 
-    generate_uses(&mut module_file)?;
+    write_module(module, &[], fn_name, options, |module| {
+        let resources = resources.iter().collect::<Vec<_>>();
+        write_resource_inserts(
+            module,
+            &resources,
+            0,
+            project_dir,
+            include_base.as_deref(),
+            options,
+        )
+    })
+}
+
+/// Writes the bundle type and the router function calling function `fn_name`.
+fn write_wrappers(
+    generated_file: &mut File,
+    fn_name: &str,
+    options: &SetsOptions,
+) -> io::Result<()> {
+    if let Some(bundle_type) = &options.bundle_type {
+        writeln!(
+            generated_file,
+            "\
+pub struct {bundle_type};
+impl ::static_files::resource::ResourceBundle<{hasher}> for {bundle_type} {{
+fn load(&self) -> ::std::collections::HashMap<&'static str, ::static_files::Resource, {hasher}> {{ {fn_name}() }}
+}}",
+            hasher = options.key_hasher.build_hasher_type(),
+        )?;
+    }
+
+    #[cfg(feature = "matchit")]
+    if let Some(router_fn) = &options.router_fn {
+        writeln!(
+            generated_file,
+            "\
+pub fn {router_fn}(router: &mut ::static_files::matchit::Router<::static_files::Resource>) -> ::std::result::Result<(), ::static_files::matchit::InsertError> {{
+::static_files::resource::insert_routes(router, {fn_name}())
+}}",
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Writes module with function `fn_name` declaring set modules `set_module_names`,
+/// resources are inserted by `write_inserts`.
+fn write_module<W, I>(
+    module_file: &mut W,
+    set_module_names: &[&str],
+    fn_name: &str,
+    options: &SetsOptions,
+    write_inserts: I,
+) -> io::Result<()>
+where
+    W: Write,
+    I: FnOnce(&mut W) -> io::Result<()>,
+{
+    generate_uses(module_file, &options.insert.resource_namespace)?;
     writeln!(
         module_file,
         "\
 use ::std::collections::HashMap;
-use ::static_files::Resource;"
+use {}::Resource;",
+        options.insert.resource_namespace,
     )?;
+    for module_use in &options.module_uses {
+        writeln!(module_file, "#[allow(unused_imports)] use {module_use};")?;
+    }
 
-    let mut modules_count = 1;
+    for set_module_name in set_module_names {
+        writeln!(module_file, "mod {set_module_name};")?;
+    }
 
-    let mut set_file = create_set_module_file(&module_dir, modules_count)?;
+    if options.prefix_argument {
+        writeln!(
+            module_file,
+            "#[allow(clippy::unreadable_literal)] pub fn {fn_name}(prefix: &str) -> HashMap<String, Resource{}> {{",
+            options.key_hasher.type_parameter(),
+        )?;
+    } else {
+        generate_function_header(
+            module_file,
+            fn_name,
+            options.key_hasher,
+            &options.insert.resource_namespace,
+        )?;
+    }
+
+    generate_variable_header(module_file, DEFAULT_VARIABLE_NAME, options.key_hasher)?;
+
+    write_inserts(module_file)?;
+
+    if options.prefix_argument {
+        writeln!(
+            module_file,
+            "{DEFAULT_VARIABLE_NAME}.into_iter().map(|(key, resource)| (format!(\"{{prefix}}{{key}}\"), resource)).collect()",
+        )?;
+    } else {
+        generate_variable_return(module_file, DEFAULT_VARIABLE_NAME)?;
+    }
+
+    generate_function_end(module_file)
+}
+
+/// Writes `MANIFEST_HASH` constant of `resources`.
+#[cfg(feature = "integrity")]
+fn write_manifest_hash(
+    generated_file: &mut File,
+    resources: &[(PathBuf, Metadata)],
+    project_dir: &Path,
+    options: &InsertOptions,
+) -> io::Result<()> {
+    let mut entries = resources
+        .iter()
+        .map(|(path, _)| Ok((options.resource_key(&project_dir, path), fs::read(path)?)))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort_unstable_by(|(key, _), (other, _)| key.cmp(other));
+    let manifest_hash = manifest_hash(
+        entries
+            .iter()
+            .map(|(key, data)| (key.as_str(), data.as_slice())),
+    );
+    writeln!(
+        generated_file,
+        "pub const MANIFEST_HASH: &str = {manifest_hash:?};"
+    )
+}
+
+/// Writes `TREE_HASH` constant of contents of `resources`.
+#[cfg(feature = "integrity")]
+fn write_tree_hash(generated_file: &mut File, resources: &[(PathBuf, Metadata)]) -> io::Result<()> {
+    let contents = resources
+        .iter()
+        .map(|(path, _)| fs::read(path))
+        .collect::<io::Result<Vec<_>>>()?;
+    let tree_hash = tree_hash(contents.iter().map(Vec::as_slice));
+    writeln!(generated_file, "pub const TREE_HASH: &str = {tree_hash:?};")
+}
+
+/// Declares module `module_name`, inline if `module` is set, and re-exports its function `fn_name`,
+/// gated by `cfg` predicate of `options` with a function returning no resources otherwise.
+fn write_module_use(
+    generated_file: &mut File,
+    module_name: &str,
+    module: Option<&[u8]>,
+    fn_name: &str,
+    options: &SetsOptions,
+) -> io::Result<()> {
+    if let Some(fn_cfg) = &options.fn_cfg {
+        writeln!(generated_file, "#[cfg({fn_cfg})]")?;
+    }
+    match module {
+        Some(module) => {
+            writeln!(generated_file, "mod {module_name} {{")?;
+            generated_file.write_all(module)?;
+            writeln!(generated_file, "}}")?;
+        }
+        None => writeln!(generated_file, "mod {module_name};")?,
+    }
+    if let Some(fn_cfg) = &options.fn_cfg {
+        writeln!(generated_file, "#[cfg({fn_cfg})]")?;
+    }
+    writeln!(generated_file, "pub use {module_name}::{fn_name};")?;
+
+    let fn_cfg = match &options.fn_cfg {
+        Some(fn_cfg) => fn_cfg,
+        None => return Ok(()),
+    };
+
+    let resource_namespace = &options.insert.resource_namespace;
+    writeln!(generated_file, "#[cfg(not({fn_cfg}))]")?;
+    if options.prefix_argument {
+        writeln!(
+            generated_file,
+            "pub fn {fn_name}(_: &str) -> ::std::collections::HashMap<String, {resource_namespace}::Resource{}> {{",
+            options.key_hasher.type_parameter(),
+        )?;
+    } else {
+        generate_function_header(
+            generated_file,
+            fn_name,
+            options.key_hasher,
+            resource_namespace,
+        )?;
+    }
+    writeln!(generated_file, "::std::collections::HashMap::default()")?;
+    generate_function_end(generated_file)
+}
+
+/// Writes constants of `resources` enabled by `options`.
+fn write_constants(
+    generated_file: &mut File,
+    resources: &[(PathBuf, Metadata)],
+    project_dir: &Path,
+    options: &SetsOptions,
+) -> io::Result<()> {
+    if options.keys_const {
+        write_keys(generated_file, resources, project_dir, &options.insert)?;
+    }
+
+    #[cfg(feature = "integrity")]
+    if options.manifest_hash {
+        write_manifest_hash(generated_file, resources, project_dir, &options.insert)?;
+    }
+
+    #[cfg(feature = "integrity")]
+    if options.tree_hash {
+        write_tree_hash(generated_file, resources)?;
+    }
+
+    Ok(())
+}
+
+/// Writes `KEYS` constant with sorted keys of `resources`.
+fn write_keys(
+    generated_file: &mut File,
+    resources: &[(PathBuf, Metadata)],
+    project_dir: &Path,
+    options: &InsertOptions,
+) -> io::Result<()> {
+    let mut keys = resources
+        .iter()
+        .map(|(path, _)| options.resource_key(&project_dir, path))
+        .collect::<Vec<_>>();
+    keys.sort_unstable();
+    writeln!(generated_file, "pub const KEYS: &[&str] = &{keys:?};")
+}
+
+/// Checks options which can't be combined or are invalid.
+fn check_options(options: &SetsOptions) -> io::Result<()> {
+    if let Some(bundle_type) = &options.bundle_type {
+        if !is_identifier(bundle_type) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("bundle type {bundle_type:?} is not a valid identifier"),
+            ));
+        }
+        if options.insert.resource_namespace != DEFAULT_RESOURCE_NAMESPACE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "bundle types require the default resource namespace",
+            ));
+        }
+        if options.prefix_argument {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "bundle types can't load functions taking a prefix argument",
+            ));
+        }
+    }
+
+    #[cfg(feature = "matchit")]
+    if let Some(router_fn) = &options.router_fn {
+        if !is_identifier(router_fn) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("router function {router_fn:?} is not a valid identifier"),
+            ));
+        }
+        if options.insert.resource_namespace != DEFAULT_RESOURCE_NAMESPACE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "router functions require the default resource namespace",
+            ));
+        }
+        if options.prefix_argument {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "router functions can't call functions taking a prefix argument",
+            ));
+        }
+    }
+
+    #[cfg(feature = "integrity")]
+    if options.manifest_hash && (options.insert.query_aliases || options.insert.cfg.is_some()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "manifest hash can't be combined with query aliases or cfg predicates",
+        ));
+    }
+
+    if let Some(pattern) = options
+        .include_globs
+        .iter()
+        .chain(&options.exclude_globs)
+        .find(|pattern| pattern.contains(&UNSUPPORTED_GLOB_CHARS[..]))
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("glob pattern {pattern:?} contains unsupported syntax"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns error listing keys of `resources` with the same content.
+///
+/// Only files of the same size are read and compared.
+fn check_duplicates(
+    resources: &[(PathBuf, Metadata)],
+    project_dir: &Path,
+    options: &InsertOptions,
+) -> io::Result<()> {
+    let mut by_size = HashMap::<_, Vec<_>>::new();
+    for (path, metadata) in resources {
+        by_size.entry(metadata.len()).or_default().push(path);
+    }
+
+    let mut duplicates = vec![];
+    for paths in by_size.into_values().filter(|paths| paths.len() > 1) {
+        let mut by_content = HashMap::<_, Vec<_>>::new();
+        for path in paths {
+            by_content
+                .entry(fs::read(path)?)
+                .or_default()
+                .push(options.resource_key(&project_dir, path));
+        }
+        duplicates.extend(by_content.into_values().filter(|keys| keys.len() > 1));
+    }
+
+    if duplicates.is_empty() {
+        return Ok(());
+    }
+    for keys in &mut duplicates {
+        keys.sort();
+    }
+    duplicates.sort();
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("resources {duplicates:?} have duplicate content"),
+    ))
+}
+
+/// Named set of resources.
+pub(crate) type ResourceSet<'a> = (String, Vec<&'a (PathBuf, Metadata)>);
+
+/// Splits `resources` into named sets with `set_split_strategy`.
+pub(crate) fn split_resources<'a, S: SetSplitStrategie + ?Sized>(
+    resources: &'a [(PathBuf, Metadata)],
+    set_split_strategy: &mut S,
+    set_module_naming: &SetModuleNaming,
+    project_dir: &Path,
+    options: &InsertOptions,
+) -> io::Result<Vec<ResourceSet<'a>>> {
+    let sets = match set_split_strategy.module_count() {
+        Some(module_count) => split_resources_by_index(
+            resources,
+            &*set_split_strategy,
+            module_count,
+            project_dir,
+            options,
+        ),
+        None => split_resources_sequentially(resources, set_split_strategy),
+    };
+
+    let mut named_sets: Vec<ResourceSet> = Vec::with_capacity(sets.len());
+    for (index, set_resources) in sets.into_iter().enumerate() {
+        let keys = set_resources
+            .iter()
+            .map(|(path, _)| options.resource_key(&project_dir, path))
+            .collect::<Vec<_>>();
+        let set_module_name = set_module_naming.module_name_with_keys(index + 1, &keys)?;
+        if named_sets.iter().any(|(name, _)| *name == set_module_name) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("set module name {set_module_name:?} is not unique"),
+            ));
+        }
+        named_sets.push((set_module_name, set_resources));
+    }
+
+    Ok(named_sets)
+}
+
+fn split_resources_sequentially<'a, S: SetSplitStrategie + ?Sized>(
+    resources: &'a [(PathBuf, Metadata)],
+    set_split_strategy: &mut S,
+) -> Vec<Vec<&'a (PathBuf, Metadata)>> {
+    let mut sets: Vec<Vec<&(PathBuf, Metadata)>> = vec![vec![]];
     let mut should_split = set_split_strategy.should_split();
 
-    for resource in &resources {
+    for resource in resources {
         let (path, metadata) = &resource;
         if should_split {
             set_split_strategy.reset();
-            modules_count += 1;
-            generate_function_end(&mut set_file)?;
-            set_file = create_set_module_file(&module_dir, modules_count)?;
+            sets.push(vec![]);
         }
         set_split_strategy.register(path, metadata);
         should_split = set_split_strategy.should_split();
 
-        generate_resource_insert(&mut set_file, &project_dir, DEFAULT_VARIABLE_NAME, resource)?;
+        if let Some(set_resources) = sets.last_mut() {
+            set_resources.push(resource);
+        }
+    }
+
+    sets
+}
+
+fn split_resources_by_index<'a, S: SetSplitStrategie + ?Sized>(
+    resources: &'a [(PathBuf, Metadata)],
+    set_split_strategy: &S,
+    module_count: usize,
+    project_dir: &Path,
+    options: &InsertOptions,
+) -> Vec<Vec<&'a (PathBuf, Metadata)>> {
+    let mut sets: Vec<Vec<&(PathBuf, Metadata)>> = vec![vec![]; module_count.max(1)];
+    let len = sets.len();
+
+    for resource in resources {
+        let (path, _) = &resource;
+        let module_index =
+            set_split_strategy.module_index(&options.resource_key(&project_dir, path));
+        sets[module_index % len].push(resource);
     }
 
-    generate_function_end(&mut set_file)?;
+    sets
+}
 
-    for module_index in 1..=modules_count {
-        writeln!(module_file, "mod set_{module_index};")?;
+/// Reports `resources` larger than the threshold of `options`, if any.
+fn warn_large_resources(
+    resources: &[(PathBuf, Metadata)],
+    project_dir: &Path,
+    options: &SetsOptions,
+) {
+    let (threshold, warn) = match options.size_warning {
+        Some(size_warning) => size_warning,
+        None => return,
+    };
+    for (path, metadata) in resources {
+        if metadata.len() > threshold {
+            warn(&format!(
+                "resource {:?} has {} bytes, more than {threshold}, consider serving it from disk or a CDN",
+                options.insert.resource_key(&project_dir, path),
+                metadata.len(),
+            ));
+        }
     }
+}
 
-    generate_function_header(&mut module_file, fn_name)?;
+/// Removes the generated file and set modules from `resources`
+/// if they are generated inside `project_dir`, so output is not embedded on the next build.
+fn exclude_generated(
+    resources: &mut Vec<(PathBuf, Metadata)>,
+    project_dir: &Path,
+    generated_filename: &Path,
+    module_name: &str,
+) {
+    let generated_dir = match generated_filename.parent() {
+        Some(parent) if parent != Path::new("") => parent,
+        _ => Path::new("."),
+    };
+    let (project_dir_canonical, generated_dir_canonical) =
+        match (project_dir.canonicalize(), generated_dir.canonicalize()) {
+            (Ok(project_dir), Ok(generated_dir)) => (project_dir, generated_dir),
+            // Missing generated directory has no collected files.
+            _ => return,
+        };
 
-    generate_variable_header(&mut module_file, DEFAULT_VARIABLE_NAME)?;
+    if let Ok(relative) = generated_dir_canonical.strip_prefix(&project_dir_canonical) {
+        let generated_dir = project_dir.join(relative);
+        let generated_file = generated_filename
+            .file_name()
+            .map(|file_name| generated_dir.join(file_name));
+        let module_dir = generated_dir.join(module_name);
+        resources.retain(|(path, _)| {
+            generated_file.as_ref() != Some(path) && !path.starts_with(&module_dir)
+        });
+    }
+}
 
-    for module_index in 1..=modules_count {
-        writeln!(
-            module_file,
-            "set_{module_index}::generate(&mut {DEFAULT_VARIABLE_NAME});",
-        )?;
+/// Appends resources of merged directories of `options` to `resources`,
+/// returns options with their keys relative to their directories.
+fn collect_merged_dirs<'a>(
+    resources: &mut Vec<(PathBuf, Metadata)>,
+    options: &'a SetsOptions,
+) -> io::Result<Cow<'a, SetsOptions>> {
+    if options.merged_dirs.is_empty() {
+        return Ok(Cow::Borrowed(options));
     }
 
-    generate_variable_return(&mut module_file, DEFAULT_VARIABLE_NAME)?;
+    let mut merged = options.clone();
+    for (dir, filter) in &options.merged_dirs {
+        for (path, metadata) in collect_resources(dir, filter.as_deref())? {
+            let key = options.insert.resource_key(dir, &path);
+            merged.insert.renamed.insert(path.clone(), key);
+            resources.push((path, metadata));
+        }
+    }
+    Ok(Cow::Owned(merged))
+}
 
-    generate_function_end(&mut module_file)?;
+/// Removes `resources` skipped by the entry action of `options`,
+/// returns options with keys of renamed ones.
+fn apply_entry_action<'a>(
+    resources: &mut Vec<(PathBuf, Metadata)>,
+    project_dir: &Path,
+    options: &'a SetsOptions,
+) -> io::Result<Cow<'a, SetsOptions>> {
+    let entry_action = match &options.entry_action {
+        Some(entry_action) => entry_action,
+        None => return Ok(Cow::Borrowed(options)),
+    };
 
-    writeln!(
-        generated_file,
-        "\
-mod {module_name};
-pub use {module_name}::{fn_name};",
+    let mut renamed = HashMap::new();
+    let mut kept = Vec::with_capacity(resources.len());
+    for (path, metadata) in resources.drain(..) {
+        let key = options.insert.resource_key(&project_dir, &path);
+        let data = fs::read(&path)?;
+        let mime_type = resource_mime_type(&path, &options.insert.mime_overrides)?;
+        let action = entry_action(&FileContext {
+            path: &path,
+            key: &key,
+            data: &data,
+            metadata: &metadata,
+            mime_type: mime_type.as_ref(),
+        });
+        match action {
+            EntryAction::Skip => continue,
+            EntryAction::Keep => {}
+            EntryAction::Rename(key) => {
+                renamed.insert(path.clone(), key);
+            }
+        }
+        kept.push((path, metadata));
+    }
+    *resources = kept;
+
+    let mut options = options.clone();
+    options.insert.renamed.extend(renamed);
+    Ok(Cow::Owned(options))
+}
+
+/// Keeps `resources` with keys matching include globs and no exclude glob of `options`.
+fn retain_globs(
+    resources: &mut Vec<(PathBuf, Metadata)>,
+    project_dir: &Path,
+    options: &SetsOptions,
+) {
+    if options.include_globs.is_empty() && options.exclude_globs.is_empty() {
+        return;
+    }
+    resources.retain(|(path, _)| {
+        let key = options.insert.resource_key(&project_dir, path);
+        let matches = |globs: &[String]| globs.iter().any(|glob| glob_match(glob, &key));
+        (options.include_globs.is_empty() || matches(&options.include_globs))
+            && !matches(&options.exclude_globs)
+    });
+}
+
+/// Keeps one of `resources` with the same key by the key conflict policy of `options`,
+/// or fails naming their paths.
+fn resolve_key_conflicts(
+    resources: &mut Vec<(PathBuf, Metadata)>,
+    project_dir: &Path,
+    options: &SetsOptions,
+) -> io::Result<()> {
+    let mut indices = HashMap::with_capacity(resources.len());
+    let mut removed = vec![false; resources.len()];
+    for (index, (path, _)) in resources.iter().enumerate() {
+        let key = options.insert.resource_key(&project_dir, path);
+        let other = match indices.insert(key.clone(), index) {
+            Some(other) => other,
+            None => continue,
+        };
+        match options.key_conflict {
+            KeyConflict::Error => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!(
+                        "resources {} and {} have the same key {key:?}",
+                        resources[other].0.display(),
+                        path.display(),
+                    ),
+                ));
+            }
+            KeyConflict::FirstWins => {
+                indices.insert(key, other);
+                removed[index] = true;
+            }
+            KeyConflict::LastWins => removed[other] = true,
+        }
+    }
+
+    let mut removed = removed.into_iter();
+    resources.retain(|_| !removed.next().unwrap_or_default());
+    Ok(())
+}
+
+/// Fails if resources with required keys of `options` are not collected, listing the missing keys.
+fn check_required_keys(
+    resources: &[(PathBuf, Metadata)],
+    project_dir: &Path,
+    options: &SetsOptions,
+) -> io::Result<()> {
+    if options.required_keys.is_empty() {
+        return Ok(());
+    }
+    let keys = resources
+        .iter()
+        .map(|(path, _)| options.insert.resource_key(&project_dir, path))
+        .collect::<HashSet<_>>();
+    let missing = options
+        .required_keys
+        .iter()
+        .filter(|key| !keys.contains(*key))
+        .collect::<Vec<_>>();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("required resources {missing:?} are missing"),
+        ))
+    }
+}
+
+/// Skips `resources` with the same hash as in the base manifest of `options`,
+/// returns sorted keys of the base manifest which are not collected, `None` without base manifest.
+#[cfg(feature = "manifest")]
+fn skip_unchanged(
+    resources: &mut Vec<(PathBuf, Metadata)>,
+    project_dir: &Path,
+    options: &SetsOptions,
+) -> io::Result<Option<Vec<String>>> {
+    let base_manifest = match &options.base_manifest {
+        Some(base_manifest) => base_manifest,
+        None => return Ok(None),
+    };
+    let mut base_hashes = read_json_manifest_hashes(base_manifest)?;
+
+    let mut unchanged = HashSet::new();
+    for (path, _) in resources.iter() {
+        let key = options.insert.resource_key(&project_dir, path);
+        if let Some(hash) = base_hashes.remove(&key) {
+            if sha256_hex(&fs::read(path)?) == hash {
+                unchanged.insert(path.clone());
+            }
+        }
+    }
+    resources.retain(|(path, _)| !unchanged.contains(path));
+
+    let mut removed_keys = base_hashes.into_keys().collect::<Vec<_>>();
+    removed_keys.sort_unstable();
+    Ok(Some(removed_keys))
+}
+
+/// Opens `generated_filename` to append function `fn_name` from module `module_name`,
+/// fails if the file already has a module or a function with the same name.
+fn open_generated_file_to_append(
+    generated_filename: &Path,
+    module_name: &str,
+    fn_name: &str,
+) -> io::Result<File> {
+    let generated = match fs::read_to_string(generated_filename) {
+        Ok(generated) => generated,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+
+    let module_declarations = [
+        format!("mod {module_name};"),
+        format!("mod {module_name} {{"),
+    ];
+    let fn_suffix = format!("::{fn_name};");
+    let duplicate = generated.lines().any(|line| {
+        module_declarations
+            .iter()
+            .any(|declaration| line == declaration)
+            || (line.starts_with("pub use ") && line.ends_with(&fn_suffix))
+    });
+    if duplicate {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!(
+                "{} already has module {module_name:?} or function {fn_name:?}",
+                generated_filename.display()
+            ),
+        ));
+    }
+
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(generated_filename)
+}
+
+pub(crate) fn module_dir(generated_filename: &Path, module_name: &str) -> PathBuf {
+    generated_filename.parent().map_or_else(
+        || PathBuf::from(module_name),
+        |parent| parent.join(module_name),
+    )
+}
+
+fn write_set_module(
+    module_dir: &Path,
+    set_module_name: &str,
+    resources: &[&(PathBuf, Metadata)],
+    first_order: usize,
+    project_dir: &Path,
+    include_base: Option<&Path>,
+    options: &SetsOptions,
+) -> io::Result<()> {
+    let mut set_file = create_set_module_file(module_dir, set_module_name, options.key_hasher)?;
+    write_resource_inserts(
+        &mut set_file,
+        resources,
+        first_order,
+        project_dir,
+        include_base,
+        options,
     )?;
+    generate_function_end(&mut set_file)
+}
+
+/// Writes inserts of `resources`, the first one has order `first_order`.
+fn write_resource_inserts<W: Write>(
+    f: &mut W,
+    resources: &[&(PathBuf, Metadata)],
+    first_order: usize,
+    project_dir: &Path,
+    include_base: Option<&Path>,
+    options: &SetsOptions,
+) -> io::Result<()> {
+    for (index, resource) in resources.iter().enumerate() {
+        let order = if options.insert.order {
+            let order = u32::try_from(first_order + index).map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidInput, "too many resources to order")
+            })?;
+            Some(order)
+        } else {
+            None
+        };
+
+        generate_resource_insert(
+            f,
+            &project_dir,
+            DEFAULT_VARIABLE_NAME,
+            resource,
+            include_base,
+            &options.insert,
+            order,
+        )?;
+    }
 
     Ok(())
 }
 
-fn create_set_module_file(module_dir: &Path, module_index: usize) -> io::Result<File> {
-    let mut set_module = File::create(module_dir.join(format!("set_{module_index}.rs")))?;
+fn create_set_module_file(
+    module_dir: &Path,
+    set_module_name: &str,
+    key_hasher: KeyHasher,
+) -> io::Result<File> {
+    let mut set_module = File::create(module_dir.join(format!("{set_module_name}.rs")))?;
 
     writeln!(
         set_module,
         "\
 #[allow(clippy::wildcard_imports)]
 use super::*;
-#[allow(clippy::unreadable_literal)]
-pub(crate) fn generate({DEFAULT_VARIABLE_NAME}: &mut HashMap<&'static str, Resource>) {{",
+#[allow(clippy::unreadable_literal, unused_variables)]
+pub(crate) fn generate({DEFAULT_VARIABLE_NAME}: &mut HashMap<&'static str, Resource{}>) {{",
+        key_hasher.type_parameter(),
     )?;
 
     Ok(set_module)
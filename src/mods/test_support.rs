@@ -0,0 +1,191 @@
+/*!
+Support for checking generation configurations in downstream tests.
+ */
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use super::resource_dir::ResourceDir;
+
+/// Generates `resources` into a temporary crate and checks it compiles with `cargo check`.
+///
+/// The crate is created in `OUT_DIR` if set, in the temporary directory otherwise,
+/// and depends only on `static-files`, so resources must use its resource type.
+/// Generated file name set on `resources` is ignored. Checks reuse the same crate,
+/// so they must not run concurrently.
+///
+/// ```rust
+/// use static_files::{resource_dir, test_support::assert_generates_compiling};
+///
+/// let mut resources = resource_dir("./tests");
+/// resources.with_generated_fn("checked").with_count_per_module(2);
+/// assert_generates_compiling(resources).unwrap();
+///
+/// let mut resources = resource_dir("./tests");
+/// resources.with_resource_namespace("crate::missing");
+/// let err = assert_generates_compiling(resources).unwrap_err();
+/// assert!(err.to_string().contains("missing"));
+/// ```
+///
+/// # Errors
+/// Returns an error with compiler diagnostics if generated code doesn't compile,
+/// or an error if generation or `cargo` fails to run.
+pub fn assert_generates_compiling(mut resources: ResourceDir) -> io::Result<()> {
+    let temp_dir = env::var("OUT_DIR").map_or_else(|_| env::temp_dir(), PathBuf::from);
+    let crate_dir = temp_dir.join("static-files-check");
+    if crate_dir.exists() {
+        fs::remove_dir_all(&crate_dir)?;
+    }
+    fs::create_dir_all(crate_dir.join("src"))?;
+
+    let features = if cfg!(feature = "fxhash") {
+        "\"fxhash\""
+    } else {
+        ""
+    };
+    fs::write(
+        crate_dir.join("Cargo.toml"),
+        format!(
+            "\
+[package]
+name = \"static-files-check\"
+version = \"0.0.0\"
+edition = \"2021\"
+publish = false
+
+[dependencies]
+static-files = {{ path = {:?}, default-features = false, features = [{features}] }}
+
+[workspace]
+",
+            env!("CARGO_MANIFEST_DIR"),
+        ),
+    )?;
+    fs::write(
+        crate_dir.join("src/lib.rs"),
+        "include!(\"generated.rs\");\n",
+    )?;
+
+    resources.with_generated_filename(crate_dir.join("src/generated.rs"));
+    resources.build()?;
+
+    let output = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".into()))
+        .arg("check")
+        .arg("--quiet")
+        .arg("--message-format=short")
+        .current_dir(&crate_dir)
+        .env(
+            "CARGO_TARGET_DIR",
+            temp_dir.join("static-files-check-target"),
+        )
+        .output()?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        ))
+    }
+}
+
+/// Generates `resources` twice into separate directories and checks the output is identical.
+///
+/// Use it to catch nondeterministic generation, like modification times of files
+/// regenerated by the build. Directories are created in `OUT_DIR` if set, in the temporary
+/// directory otherwise. Generated file name set on `resources` is ignored. Checks reuse
+/// the same directories, so they must not run concurrently.
+///
+/// ```rust
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// use static_files::{resource_dir, test_support::assert_reproducible};
+///
+/// let mut resources = resource_dir("./tests");
+/// resources.with_order(true).with_fixed_modified(1_700_000_000);
+/// assert_reproducible(&resources).unwrap();
+///
+/// fn changing_cfg(_: &str) -> Option<String> {
+///     static RUNS: AtomicUsize = AtomicUsize::new(0);
+///     Some(format!("feature = \"run{}\"", RUNS.fetch_add(1, Ordering::Relaxed)))
+/// }
+/// let mut resources = resource_dir("./tests");
+/// resources.with_cfg(changing_cfg);
+/// let err = assert_reproducible(&resources).unwrap_err();
+/// assert!(err.to_string().contains("set_1.rs differs at line"));
+/// ```
+///
+/// # Errors
+/// Returns an error with the first differing line of a generated file if the output differs,
+/// or an error if generation fails.
+pub fn assert_reproducible(resources: &ResourceDir) -> io::Result<()> {
+    let temp_dir = env::var("OUT_DIR").map_or_else(|_| env::temp_dir(), PathBuf::from);
+    let check_dir = temp_dir.join("static-files-reproducible");
+    if check_dir.exists() {
+        fs::remove_dir_all(&check_dir)?;
+    }
+
+    let mut outputs = vec![];
+    for run in ["first", "second"] {
+        let run_dir = check_dir.join(run);
+        fs::create_dir_all(&run_dir)?;
+        let mut resources = resources.clone();
+        resources.with_generated_filename(run_dir.join("generated.rs"));
+        resources.build()?;
+        outputs.push(read_tree(&run_dir)?);
+    }
+
+    let (first, second) = (&outputs[0], &outputs[1]);
+    for path in first.keys().chain(second.keys()).collect::<BTreeSet<_>>() {
+        let (content, other) = (first.get(path), second.get(path));
+        if content == other {
+            continue;
+        }
+
+        let content =
+            content.map_or_else(Default::default, |content| String::from_utf8_lossy(content));
+        let other = other.map_or_else(Default::default, |other| String::from_utf8_lossy(other));
+        let line = content
+            .lines()
+            .zip(other.lines())
+            .take_while(|(line, other_line)| line == other_line)
+            .count();
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} differs at line {}:\n- {}\n+ {}",
+                path.display(),
+                line + 1,
+                content.lines().nth(line).unwrap_or_default(),
+                other.lines().nth(line).unwrap_or_default(),
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns contents of files in `dir` by their relative paths.
+fn read_tree(dir: &Path) -> io::Result<BTreeMap<PathBuf, Vec<u8>>> {
+    let mut files = BTreeMap::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(current_dir) = dirs.pop() {
+        for entry in fs::read_dir(current_dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                let content = fs::read(&path)?;
+                files.insert(
+                    path.strip_prefix(dir).unwrap_or(&path).to_path_buf(),
+                    content,
+                );
+            }
+        }
+    }
+    Ok(files)
+}
@@ -0,0 +1,139 @@
+/*!
+Regeneration of resources on changes, for development tools running outside of `cargo`.
+*/
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+use super::{resource_dir::ResourceDir, sets::module_dir};
+
+/// Running watch started by [`watch`], stops when dropped.
+pub struct Watch {
+    _watcher: RecommendedWatcher,
+}
+
+/// Watches the resource directory of `resources` and regenerates them on changes.
+///
+/// Changes are debounced: generation runs once no more changes happen for `debounce`.
+/// Changes of the generated file and set modules are ignored, so they can be written
+/// to the resource directory.
+/// `callback` is called with the result of each generation from a background thread.
+///
+/// ```rust
+/// use std::{env, fs, path::Path, sync::mpsc, time::Duration};
+/// use static_files::{resource_dir, watch};
+///
+/// let out_dir = env::var("OUT_DIR").unwrap();
+/// let web_dir = Path::new(&out_dir).join("watch");
+/// let _ = fs::remove_dir_all(&web_dir);
+/// fs::create_dir_all(&web_dir).unwrap();
+///
+/// // Writing the generated file and sets doesn't trigger regeneration.
+/// let generated_filename = web_dir.join("generated.rs");
+/// let mut resources = resource_dir(&web_dir);
+/// resources
+///     .with_generated_filename(&generated_filename)
+///     .with_generated_fn("watched");
+///
+/// let (sender, receiver) = mpsc::channel();
+/// let watch = watch(resources, Duration::from_millis(300), move |result| {
+///     sender.send(result.is_ok()).unwrap();
+/// })
+/// .unwrap();
+///
+/// fs::write(web_dir.join("index.html"), "<html></html>").unwrap();
+/// fs::write(web_dir.join("app.js"), "").unwrap();
+///
+/// assert!(receiver.recv_timeout(Duration::from_secs(10)).unwrap());
+/// assert!(receiver.recv_timeout(Duration::from_secs(1)).is_err());
+///
+/// let set = fs::read_to_string(web_dir.join("watched_sets/set_1.rs")).unwrap();
+/// assert!(set.contains("\"index.html\""));
+/// assert!(set.contains("\"app.js\""));
+/// assert!(!set.contains("\"generated.rs\""));
+///
+/// drop(watch);
+/// ```
+///
+/// # Errors
+/// Returns an error if the resource directory can't be watched.
+pub fn watch<F>(resources: ResourceDir, debounce: Duration, mut callback: F) -> io::Result<Watch>
+where
+    F: FnMut(io::Result<()>) + Send + 'static,
+{
+    // Event paths start with the watched path, so both are canonical to compare them.
+    let resource_dir = resources.resource_dir.canonicalize()?;
+    let (generated_filename, _, module_name) = resources.generated_names();
+    let generated_paths = [
+        canonicalize_parent(&generated_filename),
+        canonicalize_parent(&module_dir(&generated_filename, &module_name)),
+    ];
+
+    let (sender, receiver) = mpsc::channel::<notify::Result<Event>>();
+    let mut watcher = notify::recommended_watcher(sender).map_err(notify_error)?;
+    watcher
+        .watch(&resource_dir, RecursiveMode::Recursive)
+        .map_err(notify_error)?;
+
+    let is_change = move |event: &notify::Result<Event>| {
+        event.as_ref().map_or(false, |event| {
+            !event.kind.is_access()
+                && event.paths.iter().any(|path| {
+                    !generated_paths
+                        .iter()
+                        .any(|generated_path| path.starts_with(generated_path))
+                })
+        })
+    };
+
+    thread::spawn(move || {
+        while let Ok(event) = receiver.recv() {
+            if !is_change(&event) {
+                continue;
+            }
+
+            loop {
+                match receiver.recv_timeout(debounce) {
+                    Ok(_) => {}
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+
+            callback(resources.clone().build());
+        }
+    });
+
+    Ok(Watch { _watcher: watcher })
+}
+
+/// Returns `path` with canonical parent directory, `path` itself if the parent doesn't exist.
+fn canonicalize_parent(path: &Path) -> PathBuf {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(file_name)) => {
+            let parent = if parent == Path::new("") {
+                Path::new(".")
+            } else {
+                parent
+            };
+            parent
+                .canonicalize()
+                .map_or_else(|_| path.to_path_buf(), |parent| parent.join(file_name))
+        }
+        _ => path.to_path_buf(),
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn notify_error(err: notify::Error) -> io::Error {
+    match err.kind {
+        notify::ErrorKind::Io(err) => err,
+        _ => io::Error::new(io::ErrorKind::Other, err.to_string()),
+    }
+}